@@ -0,0 +1,92 @@
+use crate::book::{Match, OrderBook, RestingOrder, compute_pegged_fills};
+use crate::swap_note::{Side, TradingPair};
+
+/// Uniform price at which a batch auction of a pair clears
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClearingPrice {
+    /// Price in quote units per base unit
+    pub price: f64,
+    /// Base amount both sides accept at the price
+    pub volume: u64,
+}
+
+/// Orders of `pair` taking part in an auction: live, and pegged orders only while they accept
+/// the reference price
+fn auction_orders(book: &OrderBook, pair: TradingPair) -> impl Iterator<Item = &RestingOrder> {
+    book.orders().iter().filter(move |order| {
+        order.pair() == pair && book.is_live(order) && book.accepts_reference(order)
+    })
+}
+
+/// Computes the price maximizing the volume traded between the bids and the asks of `pair`
+///
+/// The limit prices of the orders are the candidate prices. Ties are broken by the smallest
+/// imbalance between demand and supply, the price is then the middle of the tied range.
+/// Returns `None` when no bid crosses an ask.
+pub fn clearing_price(book: &OrderBook, pair: TradingPair) -> Option<ClearingPrice> {
+    let orders: Vec<&RestingOrder> = auction_orders(book, pair).collect();
+    let volume_at = |side: Side, price: f64| -> u64 {
+        orders
+            .iter()
+            .filter(|order| order.side() == side && order.accepts_price(price))
+            .map(|order| order.base_amount())
+            .sum()
+    };
+
+    let mut candidates: Vec<f64> = orders.iter().map(|order| order.price()).collect();
+    candidates.sort_by(f64::total_cmp);
+    candidates.dedup();
+
+    // (volume, imbalance, lowest price, highest price) of the best candidates
+    let mut best: Option<(u64, u64, f64, f64)> = None;
+    for price in candidates {
+        let demand = volume_at(Side::Buy, price);
+        let supply = volume_at(Side::Sell, price);
+        let volume = demand.min(supply);
+        if volume == 0 {
+            continue;
+        }
+        let imbalance = demand.abs_diff(supply);
+
+        best = match best {
+            Some((best_volume, best_imbalance, low, _))
+                if volume == best_volume && imbalance == best_imbalance =>
+            {
+                Some((volume, imbalance, low, price))
+            }
+            Some((best_volume, best_imbalance, ..))
+                if volume < best_volume
+                    || (volume == best_volume && imbalance > best_imbalance) =>
+            {
+                best
+            }
+            _ => Some((volume, imbalance, price, price)),
+        };
+    }
+
+    best.map(|(volume, _, low, high)| ClearingPrice {
+        price: (low + high) / 2.0,
+        volume,
+    })
+}
+
+/// Finds the next match of an auction of `pair` clearing at `price`
+///
+/// The best ask accepting the price is the maker and the best bid accepting it the taker,
+/// orders of the same price keep their arrival order. Orders of the same creator are not
+/// matched together. Both sides trade at the clearing price, the executor pays the difference
+/// with their own price as an improvement, see [compute_pegged_fills].
+pub fn auction_match(book: &OrderBook, pair: TradingPair, price: f64) -> Option<Match> {
+    let (mut bids, mut asks): (Vec<&RestingOrder>, Vec<&RestingOrder>) = auction_orders(book, pair)
+        .filter(|order| order.accepts_price(price))
+        .partition(|order| order.side() == Side::Buy);
+
+    bids.sort_by(|a, b| b.price().total_cmp(&a.price()));
+    asks.sort_by(|a, b| a.price().total_cmp(&b.price()));
+
+    asks.iter().find_map(|ask| {
+        bids.iter()
+            .filter(|bid| bid.creator() != ask.creator() && book.crosses_reference(bid, ask))
+            .find_map(|bid| compute_pegged_fills(ask, bid, price))
+    })
+}
@@ -0,0 +1,607 @@
+use crate::common::{OrderTerms, SwapNoteError, calculate_fee, calculate_tokens_a_for_b};
+use crate::oracle::PriceOracle;
+use crate::swap_note::{Side, SwapOrder, TradingPair};
+
+use miden_objects::account::AccountId;
+use miden_objects::asset::FungibleAsset;
+use miden_objects::block::BlockNumber;
+use miden_objects::note::{Note, NoteId};
+use std::collections::BTreeMap;
+
+/// A SWAPp order resting in the matcher's book: the note to consume and its decoded order
+#[derive(Debug, Clone)]
+pub struct RestingOrder {
+    pub note: Note,
+    pub order: SwapOrder,
+}
+
+impl RestingOrder {
+    /// Reads the order parameters of a SWAPp note, see [SwapOrder]
+    pub fn from_note(note: Note) -> Result<Self, SwapNoteError> {
+        let order = SwapOrder::try_from(&note)?;
+        Ok(Self { note, order })
+    }
+
+    pub fn id(&self) -> NoteId {
+        self.note.id()
+    }
+
+    pub fn creator(&self) -> AccountId {
+        self.order.creator()
+    }
+
+    /// Remaining amount of the offered asset held by the note
+    pub fn offered(&self) -> FungibleAsset {
+        self.order.offered
+    }
+
+    pub fn requested(&self) -> FungibleAsset {
+        self.order.requested()
+    }
+
+    pub fn terms(&self) -> &OrderTerms {
+        self.order.terms()
+    }
+
+    pub fn pair(&self) -> TradingPair {
+        self.order.pair()
+    }
+
+    pub fn side(&self) -> Side {
+        self.order.side()
+    }
+
+    /// Limit price in quote units per base unit
+    pub fn price(&self) -> f64 {
+        self.order.price()
+    }
+
+    /// Remaining amount of the base asset bought or sold by the order
+    pub fn base_amount(&self) -> u64 {
+        match self.side() {
+            Side::Sell => self.offered().amount(),
+            Side::Buy => self.requested().amount(),
+        }
+    }
+
+    /// Returns true if `account_id` is allowed to fill the order
+    pub fn can_be_filled_by(&self, account_id: AccountId) -> bool {
+        self.terms()
+            .executor
+            .is_none_or(|executor| executor == account_id)
+    }
+
+    /// Returns true if a transaction referencing `block_num` can no longer fill the order
+    ///
+    /// The transaction is included after its reference block, so the expiry block itself is too
+    /// late to reference.
+    pub fn is_expired(&self, block_num: BlockNumber) -> bool {
+        self.terms()
+            .expires_at
+            .is_some_and(|expires_at| block_num >= expires_at)
+    }
+
+    /// Returns true if the execution hint of the order allows it to be matched at `block_num`
+    ///
+    /// Orders without a hint are always live.
+    pub fn is_live(&self, block_num: BlockNumber) -> bool {
+        self.terms()
+            .execution_hint
+            .can_be_consumed(block_num)
+            .unwrap_or(true)
+    }
+
+    /// Returns true if the order would trade at `price`, in quote units per base unit
+    pub fn accepts_price(&self, price: f64) -> bool {
+        match self.side() {
+            Side::Sell => price >= self.price(),
+            Side::Buy => price <= self.price(),
+        }
+    }
+
+    /// Amount of the offered asset sent out for `filled_amount` of the requested asset, `None`
+    /// if the fill math of the note fails, see [calculate_tokens_a_for_b]
+    pub fn amount_out(&self, filled_amount: u64) -> Option<u64> {
+        calculate_tokens_a_for_b(
+            self.offered().amount(),
+            self.requested().amount(),
+            filled_amount,
+        )
+    }
+
+    /// Returns true if the note script accepts a fill of `filled_amount`: partial fills must
+    /// reach the minimum fill and are rejected by all-or-none orders, a fill completing the
+    /// order is always accepted
+    pub fn accepts_fill(&self, filled_amount: u64) -> bool {
+        match self.amount_out(filled_amount) {
+            None => false,
+            Some(amount_out) if amount_out >= self.offered().amount() => true,
+            Some(_) => {
+                !self.terms().time_in_force.is_all_or_none()
+                    && filled_amount >= self.terms().min_fill
+            }
+        }
+    }
+
+    /// Returns true if `other` trades the opposite pair at a price compatible with this order
+    pub fn crosses(&self, other: &RestingOrder) -> bool {
+        self.offered().faucet_id() == other.requested().faucet_id()
+            && self.requested().faucet_id() == other.offered().faucet_id()
+            && self.offered().amount() as u128 * other.offered().amount() as u128
+                >= self.requested().amount() as u128 * other.requested().amount() as u128
+    }
+}
+
+/// One side of a match: `order` is consumed with `filled_amount` of its requested asset as
+/// note args and releases `amount_out` of its offered asset to the executor
+///
+/// `improvement` is paid by the executor on top of the filled amount, see
+/// [compute_pegged_fills]. It reaches the order creator but leaves the remaining order as is.
+#[derive(Debug, Clone)]
+pub struct SwapFill {
+    pub order: RestingOrder,
+    pub filled_amount: u64,
+    pub amount_out: u64,
+    pub improvement: u64,
+}
+
+impl SwapFill {
+    pub fn is_complete(&self) -> bool {
+        self.amount_out >= self.order.offered().amount()
+    }
+
+    /// Amount of the requested asset sent to the note, the filled amount and the improvement
+    pub fn paid(&self) -> u64 {
+        self.filled_amount + self.improvement
+    }
+
+    /// Part of the paid amount routed to the fee recipient of the order
+    pub fn fee(&self) -> u64 {
+        self.order
+            .terms()
+            .fee
+            .map_or(0, |fee| calculate_fee(self.paid(), fee.bps.into()))
+    }
+
+    /// Part of the paid amount paid out to the order creator
+    pub fn payout(&self) -> u64 {
+        self.paid() - self.fee()
+    }
+}
+
+/// Two crossing orders settled in a single transaction by the executor account
+///
+/// The executor pays the maker first, so it needs `maker.paid()` of the maker's requested
+/// asset as inventory. Any price improvement not paid to the orders stays with the executor.
+#[derive(Debug, Clone)]
+pub struct Match {
+    pub maker: SwapFill,
+    pub taker: SwapFill,
+}
+
+/// What happens when an order would trade against a resting order of the same creator
+///
+/// The order being matched is the newest, the resting orders of the book the oldest. Orders of
+/// the same creator are never matched together, whatever the policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum SelfTradePrevention {
+    /// The order being matched is cancelled, the resting orders of its creator stay in the book
+    CancelNewest,
+    /// The resting orders of the creator it would trade against are cancelled, the order is
+    /// then matched against the rest of the book
+    CancelOldest,
+    /// Both orders stay in the book and the order is matched against the other creators only
+    #[default]
+    Skip,
+}
+
+/// In memory book of the orders received by the matcher, in arrival order
+///
+/// Orders scheduled with an execution hint rest in the book but are only matched once the
+/// block recorded by [OrderBook::remove_expired] is within their hint. Pegged orders are only
+/// matched at the reference price recorded by [OrderBook::update_reference_prices].
+#[derive(Debug, Clone, Default)]
+pub struct OrderBook {
+    orders: Vec<RestingOrder>,
+    block_num: Option<BlockNumber>,
+    reference_prices: BTreeMap<TradingPair, f64>,
+    self_trade_prevention: SelfTradePrevention,
+}
+
+impl OrderBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_self_trade_prevention(self_trade_prevention: SelfTradePrevention) -> Self {
+        Self {
+            self_trade_prevention,
+            ..Self::default()
+        }
+    }
+
+    pub fn self_trade_prevention(&self) -> SelfTradePrevention {
+        self.self_trade_prevention
+    }
+
+    pub fn insert(&mut self, order: RestingOrder) {
+        if self.get(order.id()).is_none() {
+            self.orders.push(order);
+        }
+    }
+
+    pub fn remove(&mut self, id: NoteId) -> Option<RestingOrder> {
+        let position = self.orders.iter().position(|order| order.id() == id)?;
+        Some(self.orders.remove(position))
+    }
+
+    /// Removes and returns the orders that expired at `block_num`, which becomes the reference
+    /// block of the execution hints
+    pub fn remove_expired(&mut self, block_num: BlockNumber) -> Vec<RestingOrder> {
+        self.block_num = Some(block_num);
+        let (expired, live) = std::mem::take(&mut self.orders)
+            .into_iter()
+            .partition(|order| order.is_expired(block_num));
+        self.orders = live;
+        expired
+    }
+
+    /// Returns true if `order` can be matched at the reference block, every order is live
+    /// before a block is recorded
+    pub(crate) fn is_live(&self, order: &RestingOrder) -> bool {
+        self.block_num
+            .is_none_or(|block_num| order.is_live(block_num))
+    }
+
+    /// Records the reference price of every pair in the book, pairs unknown to `oracle` have
+    /// no reference price and their pegged orders are not matched
+    pub fn update_reference_prices(&mut self, oracle: &impl PriceOracle) {
+        self.reference_prices = self
+            .orders
+            .iter()
+            .map(RestingOrder::pair)
+            .filter_map(|pair| Some((pair, oracle.reference_price(pair)?)))
+            .collect();
+    }
+
+    pub fn reference_price(&self, pair: TradingPair) -> Option<f64> {
+        self.reference_prices.get(&pair).copied()
+    }
+
+    /// Returns true if `order` is not pegged or accepts the reference price of its pair
+    pub(crate) fn accepts_reference(&self, order: &RestingOrder) -> bool {
+        !order.terms().pegged
+            || self
+                .reference_price(order.pair())
+                .is_some_and(|price| order.accepts_price(price))
+    }
+
+    /// Returns true if neither order is pegged, or if both accept the reference price
+    pub(crate) fn crosses_reference(&self, taker: &RestingOrder, maker: &RestingOrder) -> bool {
+        if !taker.terms().pegged && !maker.terms().pegged {
+            return true;
+        }
+        self.reference_price(taker.pair())
+            .is_some_and(|price| taker.accepts_price(price) && maker.accepts_price(price))
+    }
+
+    pub fn get(&self, id: NoteId) -> Option<&RestingOrder> {
+        self.orders.iter().find(|order| order.id() == id)
+    }
+
+    pub fn orders(&self) -> &[RestingOrder] {
+        &self.orders
+    }
+
+    pub fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+
+    /// Finds the best resting counter order for `taker` and computes the fills
+    ///
+    /// Resting orders are ranked by price (most offered per requested unit first), then by
+    /// arrival. The match executes at the maker's price. Pegged orders only match counter orders
+    /// accepting the reference price of their pair and execute at that price, see
+    /// [OrderBook::match_orders]. Orders of the creator of `taker` are left out, see
+    /// [OrderBook::prevent_self_trade].
+    pub fn find_match(&self, taker: &RestingOrder) -> Option<Match> {
+        self.candidates(taker)
+            .into_iter()
+            .find_map(|maker| self.match_orders(maker, taker))
+    }
+
+    /// Computes the fills of two crossing orders, at the reference price of their pair when
+    /// either is pegged (see [compute_pegged_fills]) and at the maker's price otherwise (see
+    /// [compute_fills])
+    pub fn match_orders(&self, maker: &RestingOrder, taker: &RestingOrder) -> Option<Match> {
+        if maker.terms().pegged || taker.terms().pegged {
+            let price = self.reference_price(taker.pair())?;
+            return compute_pegged_fills(maker, taker, price);
+        }
+        compute_fills(maker, taker)
+    }
+
+    /// Finds several resting counter orders filling `taker` together in a single transaction,
+    /// see [compute_multi_fills]
+    ///
+    /// Returns `None` unless at least two counter orders take part, a single one is found by
+    /// [OrderBook::find_match]. Pegged orders are left out, they execute at the reference price.
+    pub fn find_multi_match(&self, taker: &RestingOrder) -> Option<MultiMatch> {
+        if taker.terms().pegged {
+            return None;
+        }
+        let makers: Vec<&RestingOrder> = self
+            .candidates(taker)
+            .into_iter()
+            .filter(|maker| !maker.terms().pegged)
+            .collect();
+        compute_multi_fills(&makers, taker).filter(|multi_match| multi_match.makers.len() >= 2)
+    }
+
+    /// Resting orders of the creator of `taker` it would trade against before any order of
+    /// another creator, best price first
+    pub fn self_trades(&self, taker: &RestingOrder) -> Vec<&RestingOrder> {
+        self.crossing(taker)
+            .into_iter()
+            .take_while(|maker| maker.creator() == taker.creator())
+            .collect()
+    }
+
+    /// Applies the self-trade prevention policy of the book before `taker` is matched, see
+    /// [SelfTradePrevention]
+    ///
+    /// Returns the cancelled orders, removed from the book. `taker` is returned when it is
+    /// cancelled, the caller must then stop matching it.
+    pub fn prevent_self_trade(&mut self, taker: &RestingOrder) -> Vec<RestingOrder> {
+        let self_trades: Vec<NoteId> = self
+            .self_trades(taker)
+            .into_iter()
+            .map(RestingOrder::id)
+            .collect();
+        if self_trades.is_empty() {
+            return Vec::new();
+        }
+
+        match self.self_trade_prevention {
+            SelfTradePrevention::CancelNewest => {
+                self.remove(taker.id());
+                vec![taker.clone()]
+            }
+            SelfTradePrevention::CancelOldest => self_trades
+                .into_iter()
+                .filter_map(|id| self.remove(id))
+                .collect(),
+            SelfTradePrevention::Skip => Vec::new(),
+        }
+    }
+
+    /// Resting counter orders of other creators crossing `taker`, best price first
+    fn candidates(&self, taker: &RestingOrder) -> Vec<&RestingOrder> {
+        self.crossing(taker)
+            .into_iter()
+            .filter(|maker| maker.creator() != taker.creator())
+            .collect()
+    }
+
+    /// Resting orders crossing `taker`, best price first
+    fn crossing(&self, taker: &RestingOrder) -> Vec<&RestingOrder> {
+        if !self.is_live(taker) {
+            return Vec::new();
+        }
+
+        let mut candidates: Vec<&RestingOrder> = self
+            .orders
+            .iter()
+            .filter(|maker| maker.id() != taker.id() && taker.crosses(maker))
+            .filter(|maker| self.is_live(maker))
+            .filter(|maker| self.crosses_reference(taker, maker))
+            .collect();
+
+        // stable sort keeps arrival order between equally priced orders
+        candidates.sort_by(|a, b| {
+            let a_price = a.offered().amount() as u128 * b.requested().amount() as u128;
+            let b_price = b.offered().amount() as u128 * a.requested().amount() as u128;
+            b_price.cmp(&a_price)
+        });
+
+        candidates
+    }
+}
+
+/// Computes the fill amounts of two crossing orders
+///
+/// The maker receives as much of the taker's offered asset as both sides allow, the taker is
+/// then paid with what the maker released. Returns `None` when rounding in the note fill math
+/// would leave the executor short or when a fill is not accepted by its order, see
+/// [RestingOrder::accepts_fill].
+pub fn compute_fills(maker: &RestingOrder, taker: &RestingOrder) -> Option<Match> {
+    let maker_filled = maker.requested().amount().min(taker.offered().amount());
+    if maker_filled == 0 {
+        return None;
+    }
+    let maker_out = maker.amount_out(maker_filled)?;
+
+    let taker_filled = maker_out.min(taker.requested().amount());
+    if taker_filled == 0 {
+        return None;
+    }
+    let taker_out = taker.amount_out(taker_filled)?;
+    if maker_out > maker.offered().amount() || taker_out > taker.offered().amount() {
+        return None;
+    }
+    if taker_out < maker_filled || maker_out < taker_filled {
+        return None;
+    }
+    if !maker.accepts_fill(maker_filled) || !taker.accepts_fill(taker_filled) {
+        return None;
+    }
+
+    Some(Match {
+        maker: SwapFill {
+            order: maker.clone(),
+            filled_amount: maker_filled,
+            amount_out: maker_out,
+            improvement: 0,
+        },
+        taker: SwapFill {
+            order: taker.clone(),
+            filled_amount: taker_filled,
+            amount_out: taker_out,
+            improvement: 0,
+        },
+    })
+}
+
+/// Computes the fill amounts of two crossing orders executing at the reference `price` of
+/// their pair, in quote units per base unit
+///
+/// Each note is filled at its own price and the executor pays the difference with the
+/// reference price as an improvement on top of the fill, so both creators trade at the
+/// reference price (up to rounding down) and the executor keeps no spread. What an order did
+/// not spend of its offered asset stays in its SWAPp' at its limit price. Returns `None` under
+/// the same conditions as [compute_fills].
+pub fn compute_pegged_fills(
+    maker: &RestingOrder,
+    taker: &RestingOrder,
+    price: f64,
+) -> Option<Match> {
+    let (seller, buyer) = match maker.side() {
+        Side::Sell => (maker, taker),
+        Side::Buy => (taker, maker),
+    };
+
+    // base traded, limited by what the buyer can pay at the reference price
+    let affordable = (buyer.offered().amount() as f64 / price) as u64;
+    let base = seller
+        .offered()
+        .amount()
+        .min(buyer.requested().amount())
+        .min(affordable);
+    if base == 0 {
+        return None;
+    }
+
+    // the seller note is filled at its price, the base it releases is paid at the reference price
+    let seller_filled = if base == seller.offered().amount() {
+        seller.requested().amount()
+    } else {
+        (base as u128 * seller.requested().amount() as u128 / seller.offered().amount() as u128)
+            as u64
+    };
+    if seller_filled == 0 {
+        return None;
+    }
+    let seller_out = seller.amount_out(seller_filled)?;
+    let quote = (seller_out as f64 * price) as u64;
+
+    // the buyer note is filled at its price for that quote, the rest of the base is improvement
+    let buyer_filled = (quote as u128 * buyer.requested().amount() as u128
+        / buyer.offered().amount() as u128) as u64;
+    if buyer_filled == 0 {
+        return None;
+    }
+    let buyer_out = buyer.amount_out(buyer_filled)?;
+    if seller_out > seller.offered().amount() || buyer_out > buyer.offered().amount() {
+        return None;
+    }
+    let seller_improvement = buyer_out.checked_sub(seller_filled)?;
+    let buyer_improvement = seller_out.checked_sub(buyer_filled)?;
+    if !seller.accepts_fill(seller_filled) || !buyer.accepts_fill(buyer_filled) {
+        return None;
+    }
+
+    let seller_fill = SwapFill {
+        order: seller.clone(),
+        filled_amount: seller_filled,
+        amount_out: seller_out,
+        improvement: seller_improvement,
+    };
+    let buyer_fill = SwapFill {
+        order: buyer.clone(),
+        filled_amount: buyer_filled,
+        amount_out: buyer_out,
+        improvement: buyer_improvement,
+    };
+    let (maker, taker) = match maker.side() {
+        Side::Sell => (seller_fill, buyer_fill),
+        Side::Buy => (buyer_fill, seller_fill),
+    };
+    Some(Match { maker, taker })
+}
+
+/// Several makers filling one taker in a single transaction
+///
+/// The taker note is consumed once, with the sum of what the makers released as its filled
+/// amount, so a large order is filled against many small ones without an intermediate SWAPp'.
+#[derive(Debug, Clone)]
+pub struct MultiMatch {
+    pub makers: Vec<SwapFill>,
+    pub taker: SwapFill,
+}
+
+/// Computes the fills of `taker` against `makers`, taken in order
+///
+/// Every maker is filled with as much of the taker's offered asset as is left, with the fill
+/// math of [compute_fills]. A maker is skipped when its fill is not accepted or would leave the
+/// executor short, and no maker is added once the taker is completely filled. Returns `None`
+/// when no maker takes part or when the taker does not accept its fill.
+pub fn compute_multi_fills(makers: &[&RestingOrder], taker: &RestingOrder) -> Option<MultiMatch> {
+    let mut fills: Vec<SwapFill> = Vec::new();
+    // paid to the makers in the taker's offered asset, released by them in its requested asset
+    let (mut paid, mut released) = (0u64, 0u64);
+
+    for maker in makers {
+        if released >= taker.requested().amount() {
+            break;
+        }
+
+        let maker_filled = maker
+            .requested()
+            .amount()
+            .min(taker.offered().amount().saturating_sub(paid));
+        if maker_filled == 0 || !maker.accepts_fill(maker_filled) {
+            continue;
+        }
+        let Some(maker_out) = maker.amount_out(maker_filled).filter(|out| *out > 0) else {
+            continue;
+        };
+
+        let taker_filled = (released + maker_out).min(taker.requested().amount());
+        if taker
+            .amount_out(taker_filled)
+            .is_none_or(|taker_out| taker_out < paid + maker_filled)
+        {
+            continue;
+        }
+
+        paid += maker_filled;
+        released += maker_out;
+        fills.push(SwapFill {
+            order: (*maker).clone(),
+            filled_amount: maker_filled,
+            amount_out: maker_out,
+            improvement: 0,
+        });
+    }
+
+    let taker_filled = released.min(taker.requested().amount());
+    if fills.is_empty() || !taker.accepts_fill(taker_filled) {
+        return None;
+    }
+    let taker_out = taker.amount_out(taker_filled)?;
+
+    Some(MultiMatch {
+        makers: fills,
+        taker: SwapFill {
+            order: taker.clone(),
+            filled_amount: taker_filled,
+            amount_out: taker_out,
+            improvement: 0,
+        },
+    })
+}
@@ -0,0 +1,317 @@
+use crate::swap_note::{SWAPP_NUM_INPUTS, SwapNoteBuilder, SwapNoteInputs};
+
+use miden_lib::note::utils::build_p2id_recipient;
+use miden_objects::account::AccountId;
+use miden_objects::asset::FungibleAsset;
+use miden_objects::block::BlockNumber;
+use miden_objects::note::{Note, NoteAssets, NoteDetails, NoteExecutionHint, NoteTag, NoteType};
+use miden_objects::{
+    AccountIdError, AssetError, Felt, Hasher, NoteError, Word, ZERO, asset::Asset,
+};
+use thiserror::Error;
+
+/// Fees are expressed in basis points of the requested asset sent in by the consumer
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// Domain tag merged into the payout P2ID serial number to derive the fee P2ID one, "fee"
+const FEE_SERIAL_DOMAIN: u64 = 0x666565;
+
+#[derive(Error, Debug)]
+pub enum SwapNoteError {
+    #[error("note is not a SWAPp order: expected {SWAPP_NUM_INPUTS} inputs, got {0}")]
+    InvalidInputs(usize),
+
+    #[error("fee of {0} basis points must be below {BPS_DENOMINATOR}")]
+    InvalidFee(u64),
+
+    #[error("expiry block {0} is not a valid block number")]
+    InvalidExpiry(u64),
+
+    #[error("{0:?} SWAPp notes are not supported")]
+    UnsupportedNoteType(NoteType),
+
+    #[error("SWAPp note must hold exactly one fungible asset")]
+    InvalidAssets,
+
+    #[error("offered and requested amounts of a SWAPp order must not be zero")]
+    ZeroAmount,
+
+    #[error("note does not run a SWAPp script")]
+    UnknownScript,
+
+    #[error("invalid account id in note inputs: {0}")]
+    InvalidAccountId(#[from] AccountIdError),
+
+    #[error("invalid asset: {0}")]
+    InvalidAsset(#[from] AssetError),
+
+    #[error("note error: {0}")]
+    Note(#[from] NoteError),
+}
+
+/// How long an order stays open and whether it accepts partial fills
+///
+/// Stored as bit flags in the order flags input of the SWAPp note. Only the all-or-none flag is
+/// enforced by the note script, the immediate flag tells the matcher not to rest the order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum TimeInForce {
+    /// Rests in the book until filled, cancelled or expired, partial fills allowed
+    #[default]
+    #[cfg_attr(feature = "clap", value(name = "gtc"))]
+    GoodTillCancelled,
+    /// Rests in the book until it can be filled completely in a single fill
+    #[cfg_attr(feature = "clap", value(name = "aon"))]
+    AllOrNone,
+    /// Filled completely on arrival or dropped by the matcher
+    #[cfg_attr(feature = "clap", value(name = "fok"))]
+    FillOrKill,
+}
+
+impl TimeInForce {
+    const ALL_OR_NONE: u64 = 1;
+    const IMMEDIATE: u64 = 2;
+
+    pub fn flags(&self) -> u64 {
+        match self {
+            TimeInForce::GoodTillCancelled => 0,
+            TimeInForce::AllOrNone => Self::ALL_OR_NONE,
+            TimeInForce::FillOrKill => Self::ALL_OR_NONE | Self::IMMEDIATE,
+        }
+    }
+
+    pub fn from_flags(flags: u64) -> Self {
+        if flags & Self::IMMEDIATE != 0 {
+            TimeInForce::FillOrKill
+        } else if flags & Self::ALL_OR_NONE != 0 {
+            TimeInForce::AllOrNone
+        } else {
+            TimeInForce::GoodTillCancelled
+        }
+    }
+
+    /// Returns true if the note script rejects partial fills
+    pub fn is_all_or_none(&self) -> bool {
+        self.flags() & Self::ALL_OR_NONE != 0
+    }
+}
+
+/// Order flag of pegged orders, see [OrderTerms::pegged]. Ignored by the note script.
+pub const PEGGED_FLAG: u64 = 4;
+
+/// Fee routed by the SWAPp note script to `recipient` on every fill
+///
+/// The fee is taken from the requested asset sent in by the consumer, so the creator receives
+/// the filled amount minus the fee. See [calculate_fee].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderFee {
+    pub bps: u16,
+    pub recipient: AccountId,
+}
+
+/// Constraints enforced by the SWAPp note script on the consumers of an order
+///
+/// The default places no constraint: the order never expires and accepts fills of any size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderTerms {
+    /// Last block a fill can be included in, other consumers must reference an earlier block
+    /// and only the creator can consume the note afterwards. Stored as 0 when the order never
+    /// expires.
+    pub expires_at: Option<BlockNumber>,
+    /// Smallest amount of the requested asset a partial fill must send in, a fill completing
+    /// the order is always accepted
+    pub min_fill: u64,
+    pub time_in_force: TimeInForce,
+    /// Only account other than the creator allowed to consume the note, usually the dark pool
+    /// matcher. Stored as [0, 0] when any account can fill the order.
+    pub executor: Option<AccountId>,
+    /// Stored as 0 basis points to a [0, 0] recipient when the order pays no fee
+    pub fee: Option<OrderFee>,
+    /// Account receiving the proceeds of every fill, e.g. a cold wallet. Stored as [0, 0] when
+    /// the proceeds are paid out to the creator.
+    pub payout: Option<AccountId>,
+    /// Carried in the metadata of the order and of every SWAPp', e.g. a client order id
+    pub aux: Felt,
+    /// Execution hint of the order and of every SWAPp'
+    pub execution_hint: NoteExecutionHint,
+    /// Only matched while the reference price of the matcher is within the limit price of the
+    /// order, against counter orders that also accept the reference price, and executed at the
+    /// reference price: the executor pays the difference with the note price as a price
+    /// improvement through the note args.
+    pub pegged: bool,
+}
+
+impl Default for OrderTerms {
+    fn default() -> Self {
+        Self {
+            expires_at: None,
+            min_fill: 0,
+            time_in_force: TimeInForce::default(),
+            executor: None,
+            fee: None,
+            payout: None,
+            aux: ZERO,
+            execution_hint: NoteExecutionHint::always(),
+            pegged: false,
+        }
+    }
+}
+
+/// Reconstructs the P2ID note paid out to the payout account of a SWAPp order
+///
+/// The PRIVATE_SWAPp script emits the P2ID note as a private note, so its details never reach
+/// the creator over the network. Everything needed to rebuild it is known to the creator:
+///  - the P2ID serial number is hash(SWAPP_SERIAL_NUM || [swap_count + 1, inputs[9..12]])
+///  - the serial number of the n-th SWAPp' is the original one with the last element increased by n
+///  - the recipient is the payout account stored in the note inputs, or the creator if unset
+///  - the amount is the paid amount minus the fee of the order
+///
+/// `order` is the originally created SWAPp note, `fill_number` is the swap count of the note
+/// that was consumed (0 for the original order) and `paid_amount` the amount of the requested
+/// asset sent in by the consumer: the filled amount plus any price improvement.
+pub fn reconstruct_p2id_note(
+    order: &Note,
+    fill_number: u64,
+    paid_amount: u64,
+) -> Result<(NoteDetails, NoteTag), SwapNoteError> {
+    let inputs = SwapNoteInputs::decode(order.inputs())?;
+
+    let fee = inputs
+        .terms
+        .fee
+        .map_or(0, |fee| calculate_fee(paid_amount, fee.bps.into()));
+
+    let recipient =
+        build_p2id_recipient(inputs.payout_account(), p2id_serial_num(order, fill_number))?;
+    let asset = FungibleAsset::new(inputs.requested.faucet_id(), paid_amount - fee)?;
+    let assets = NoteAssets::new(vec![asset.into()])?;
+
+    Ok((NoteDetails::new(assets, recipient), inputs.p2id_tag()?))
+}
+
+/// Reconstructs the P2ID note carrying the fee of a SWAPp fill to the fee recipient
+///
+/// The serial number of the fee P2ID is the serial number of the creator's P2ID merged with a
+/// domain tag, so the two notes never share an id. See [reconstruct_p2id_note] for the
+/// arguments. Returns `None` if the fill pays no fee, in which case no note is created.
+pub fn reconstruct_fee_note(
+    order: &Note,
+    fill_number: u64,
+    paid_amount: u64,
+) -> Result<Option<(AccountId, NoteDetails, NoteTag)>, SwapNoteError> {
+    let inputs = SwapNoteInputs::decode(order.inputs())?;
+
+    let (Some(fee), Some(fee_tag)) = (inputs.terms.fee, inputs.fee_tag()?) else {
+        return Ok(None);
+    };
+    let amount = calculate_fee(paid_amount, fee.bps.into());
+    if amount == 0 {
+        return Ok(None);
+    }
+
+    let domain: Word = [ZERO, ZERO, ZERO, Felt::new(FEE_SERIAL_DOMAIN)];
+    let serial_num = Hasher::merge(&[p2id_serial_num(order, fill_number).into(), domain.into()]);
+    let recipient = build_p2id_recipient(fee.recipient, serial_num.into())?;
+    let asset = FungibleAsset::new(inputs.requested.faucet_id(), amount)?;
+    let assets = NoteAssets::new(vec![asset.into()])?;
+
+    Ok(Some((
+        fee.recipient,
+        NoteDetails::new(assets, recipient),
+        fee_tag,
+    )))
+}
+
+/// Returns the fee in basis points of `filled_amount` taken by the SWAPp script
+///
+/// Mirrors `calculate_fee` of the script, which splits the amount to avoid overflows.
+pub fn calculate_fee(filled_amount: u64, fee_bps: u64) -> u64 {
+    let quotient = filled_amount / BPS_DENOMINATOR;
+    let remainder = filled_amount % BPS_DENOMINATOR;
+    quotient * fee_bps + remainder * fee_bps / BPS_DENOMINATOR
+}
+
+/// Serial number of the P2ID notes emitted when the `fill_number`-th SWAPp' of `order` is filled
+fn p2id_serial_num(order: &Note, fill_number: u64) -> Word {
+    let inputs = order.inputs().values();
+
+    let mut swap_serial_num = order.serial_num();
+    swap_serial_num[3] += Felt::new(fill_number);
+
+    let swap_count: Word = [
+        inputs[8] + Felt::new(fill_number + 1),
+        inputs[9],
+        inputs[10],
+        inputs[11],
+    ];
+    Hasher::merge(&[swap_serial_num.into(), swap_count.into()]).into()
+}
+
+/// Generates the SWAPp' note emitted when `order` is partially filled
+///
+/// Mirrors the SWAPp scripts: the requested amount is reduced by `filled_amount`, the offered
+/// amount by `amount_out`, the swap count is incremented and the last element of the serial
+/// number is increased by one. All other inputs, the tag, the aux and the execution hint are
+/// carried over unchanged.
+///
+/// Returns `None` if the fill completes the order, in which case no SWAPp' is created.
+pub fn create_swap_note_successor(
+    order: &Note,
+    consumer: AccountId,
+    filled_amount: u64,
+    amount_out: u64,
+) -> Result<Option<Note>, SwapNoteError> {
+    let inputs = SwapNoteInputs::decode(order.inputs())?;
+
+    let offered_asset = match order.assets().iter().next() {
+        Some(Asset::Fungible(asset)) if order.assets().num_assets() == 1 => *asset,
+        _ => return Err(SwapNoteError::InvalidAssets),
+    };
+    if amount_out >= offered_asset.amount() {
+        return Ok(None);
+    }
+
+    let requested_remaining = inputs.requested.amount().saturating_sub(filled_amount);
+    let requested_asset = FungibleAsset::new(inputs.requested.faucet_id(), requested_remaining)?;
+    let offered_remaining = FungibleAsset::new(
+        offered_asset.faucet_id(),
+        offered_asset.amount() - amount_out,
+    )?;
+
+    let mut serial_num = order.serial_num();
+    serial_num[3] += Felt::new(1);
+
+    let successor = SwapNoteBuilder::new(
+        inputs.creator,
+        offered_remaining.into(),
+        requested_asset.into(),
+        serial_num,
+    )
+    .sender(consumer)
+    .note_type(order.metadata().note_type())
+    .tag(inputs.swapp_tag)
+    .fill_number(inputs.fill_number + 1)
+    .terms(inputs.terms)
+    .build()?;
+
+    Ok(Some(successor))
+}
+
+/// Returns the amount of token_a sent to the consumer for `tokens_b_in` of token_b
+///
+/// Mirrors `calculate_tokens_a_for_b` of the SWAPp scripts, including the 1e5 fixed point
+/// precision, so the matcher can predict the outcome of a fill.
+///
+/// Returns `None` when the script would divide by zero or wrap around, such fills are never
+/// matched.
+pub fn calculate_tokens_a_for_b(tokens_a: u64, tokens_b: u64, tokens_b_in: u64) -> Option<u64> {
+    const FACTOR: u64 = 100_000;
+
+    if tokens_b > tokens_a {
+        let ratio = tokens_b.checked_mul(FACTOR)?.checked_div(tokens_a)?;
+        tokens_b_in.checked_mul(FACTOR)?.checked_div(ratio)
+    } else {
+        let ratio = tokens_a.checked_mul(FACTOR)?.checked_div(tokens_b)?;
+        Some(ratio.checked_mul(tokens_b_in)? / FACTOR)
+    }
+}
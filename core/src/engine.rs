@@ -0,0 +1,665 @@
+use crate::auction::{ClearingPrice, auction_match, clearing_price};
+use crate::book::{Match, MultiMatch, OrderBook, RestingOrder, SelfTradePrevention, SwapFill};
+use crate::common::{SwapNoteError, TimeInForce};
+use crate::events::{DepthSnapshot, MarketEvent, book_pairs};
+use crate::market::{LastTrades, MarketSummary};
+use crate::oracle::StaticPrices;
+use crate::ring::{Ring, find_ring};
+use crate::swap_note::{PRIVATE_SWAPP_SCRIPT_ROOT, PUBLIC_SWAPP_SCRIPT_ROOT, TradingPair};
+
+use miden_objects::account::AccountId;
+use miden_objects::block::BlockNumber;
+use miden_objects::note::{Note, NoteExecutionHint, NoteId};
+use std::collections::VecDeque;
+use thiserror::Error;
+
+/// Roots of the note scripts accepted by the matcher, the same for debug and production builds
+pub const ALLOWED_SCRIPT_ROOTS: [&str; 2] = [PRIVATE_SWAPP_SCRIPT_ROOT, PUBLIC_SWAPP_SCRIPT_ROOT];
+
+/// When the matcher matches the orders it receives
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum MatchingMode {
+    /// Orders are matched on arrival at the price of the resting order
+    #[default]
+    Continuous,
+    /// Orders rest until the next auction, which settles the crossing orders of every pair
+    /// around a uniform clearing price. Arrival times within a batch are not revealed: orders
+    /// accepted during a batch are published with its auction, and the depth only changes at
+    /// auctions.
+    Batch,
+}
+
+/// Fee the matcher requires from the orders it accepts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FeePolicy {
+    /// Smallest fee in basis points, no fee is required when 0
+    pub min_bps: u16,
+    /// Account the fees must be paid to, any recipient when not set
+    pub recipient: Option<AccountId>,
+}
+
+impl FeePolicy {
+    pub fn accepts(&self, order: &RestingOrder) -> bool {
+        if self.min_bps == 0 {
+            return true;
+        }
+        order.terms().fee.is_some_and(|fee| {
+            fee.bps >= self.min_bps && self.recipient.is_none_or(|r| r == fee.recipient)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MatcherConfig {
+    /// Account settling the matches, orders are only collected when not set
+    pub executor: Option<AccountId>,
+    pub mode: MatchingMode,
+    pub fee_policy: FeePolicy,
+    pub self_trade_prevention: SelfTradePrevention,
+    /// Tick the published depth levels are coarsened to, market queries cannot ask for a finer
+    /// one, see [DepthSnapshot::coarsened]
+    pub depth_tick: Option<f64>,
+}
+
+#[derive(Error, Debug)]
+pub enum Rejection {
+    #[error("script root {0} is not a SWAPp script")]
+    UnknownScript(String),
+
+    #[error("invalid order: {0}")]
+    InvalidOrder(#[from] SwapNoteError),
+
+    #[error("sender is not the creator")]
+    NotCreator,
+
+    #[error("fee below the matcher fee")]
+    FeeTooLow,
+
+    #[error("restricted to another executor")]
+    OtherExecutor,
+
+    #[error("expired")]
+    Expired,
+}
+
+/// Why an order left the book without being filled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CancelReason {
+    /// The order can no longer be filled by the executor
+    Expired,
+    /// A fill-or-kill order was not filled on arrival or by its auction
+    FillOrKill,
+    /// The self-trade prevention policy cancelled the order, see [SelfTradePrevention]
+    SelfTrade,
+}
+
+/// Orders consumed together in a single transaction by the executor
+#[derive(Debug, Clone)]
+pub enum SettlementPlan {
+    Match(Box<Match>),
+    MultiMatch(Box<MultiMatch>),
+    Ring(Ring),
+}
+
+impl SettlementPlan {
+    /// Fills in the order their notes are consumed, makers before the taker
+    pub fn fills(&self) -> Vec<&SwapFill> {
+        match self {
+            SettlementPlan::Match(matched) => vec![&matched.maker, &matched.taker],
+            SettlementPlan::MultiMatch(multi_match) => multi_match
+                .makers
+                .iter()
+                .chain([&multi_match.taker])
+                .collect(),
+            SettlementPlan::Ring(ring) => ring.fills.iter().collect(),
+        }
+    }
+}
+
+/// Messages handed to the [MatcherCore] by its host
+#[derive(Debug)]
+pub enum Input {
+    /// Order received from a user
+    ///
+    /// `block_num` is the latest block synced by the host, the orders expired at this block are
+    /// dropped first. `transport_key` is handed back when the order is accepted.
+    SubmitOrder {
+        note: Box<Note>,
+        transport_key: [u8; 32],
+        block_num: Option<BlockNumber>,
+    },
+    /// Periodic check dropping the orders expired at `block_num`, then matching the scheduled
+    /// and pegged orders in continuous mode
+    ExpiryCheck { block_num: Option<BlockNumber> },
+    /// End of a batch, runs the auction of every pair in batch mode
+    BatchAuction { block_num: Option<BlockNumber> },
+    /// Replaces the reference prices of pegged orders
+    ReferencePrices(StaticPrices),
+    /// Updates the reference price of a single pair
+    ReferencePrice { pair: TradingPair, price: f64 },
+    /// The last [Output::Settle] was settled, with the SWAPp' of every order in the order of
+    /// [SettlementPlan::fills]
+    Settled { successors: Vec<Option<Note>> },
+    /// The last [Output::Settle] could not be settled, its orders were not consumed
+    SettlementFailed,
+    /// Answered with [Output::Market], as of the last auction in batch mode. The depth is
+    /// coarsened to the larger of `tick` and the configured depth tick.
+    QueryMarket {
+        pair: TradingPair,
+        tick: Option<f64>,
+    },
+    /// Answered with [Output::Depth], as of the last auction in batch mode
+    QueryDepth,
+}
+
+/// Messages returned by the [MatcherCore] for its host to carry out
+#[derive(Debug)]
+pub enum Output {
+    /// The order was accepted, notes for its creator are sealed to `transport_key`
+    Accepted {
+        id: NoteId,
+        creator: AccountId,
+        transport_key: [u8; 32],
+    },
+    Rejected {
+        id: NoteId,
+        reason: Rejection,
+    },
+    Cancelled {
+        id: NoteId,
+        reason: CancelReason,
+    },
+    /// Market data for the subscribers of the matcher
+    Event(MarketEvent),
+    AuctionCleared {
+        pair: TradingPair,
+        clearing: ClearingPrice,
+    },
+    /// Orders to consume with the executor account, always the last output. The host must
+    /// answer with [Input::Settled] or [Input::SettlementFailed] before any other input.
+    Settle(SettlementPlan),
+    Market(MarketSummary),
+    Depth(Vec<DepthSnapshot>),
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum CoreError {
+    #[error("a settlement is pending")]
+    SettlementPending,
+
+    #[error("no settlement is pending")]
+    NoSettlementPending,
+}
+
+// Work left to do by the core, run in order until a settlement is needed
+#[derive(Debug)]
+enum Task {
+    Match(Box<RestingOrder>),
+    MatchScheduled(VecDeque<NoteId>),
+    Auctions(VecDeque<TradingPair>),
+    Auction { pair: TradingPair, price: f64 },
+    DropFillOrKill,
+    MatchRings,
+    PublishDepth(Option<TradingPair>),
+}
+
+// Where matching resumes once a settlement is answered
+#[derive(Debug)]
+enum Resume {
+    Order(Box<RestingOrder>),
+    Auction { pair: TradingPair, price: f64 },
+    Rings,
+}
+
+/// Deterministic state machine of the matcher: the book, the last trades and the reference
+/// prices
+///
+/// Every [Input] is handled to completion and returns the [Output]s for the host, unless a
+/// settlement is needed: matching then stops at an [Output::Settle] and resumes with the answer
+/// of the host. The same inputs always give the same outputs.
+#[derive(Debug)]
+pub struct MatcherCore {
+    config: MatcherConfig,
+    book: OrderBook,
+    // book as published by the last auction, in batch mode
+    auction_book: OrderBook,
+    // acceptances of the current batch, published with its auction
+    held: Vec<(NoteId, MarketEvent)>,
+    last_trades: LastTrades,
+    prices: StaticPrices,
+    tasks: VecDeque<Task>,
+    pending: Option<(SettlementPlan, Resume)>,
+}
+
+impl MatcherCore {
+    pub fn new(config: MatcherConfig) -> Self {
+        Self {
+            config,
+            book: OrderBook::with_self_trade_prevention(config.self_trade_prevention),
+            auction_book: OrderBook::new(),
+            held: Vec::new(),
+            last_trades: LastTrades::new(),
+            prices: StaticPrices::new(),
+            tasks: VecDeque::new(),
+            pending: None,
+        }
+    }
+
+    pub fn config(&self) -> &MatcherConfig {
+        &self.config
+    }
+
+    pub fn book(&self) -> &OrderBook {
+        &self.book
+    }
+
+    /// Returns true if the last [Output::Settle] was not answered yet
+    pub fn is_settling(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    pub fn handle(&mut self, input: Input) -> Result<Vec<Output>, CoreError> {
+        let mut outputs = Vec::new();
+
+        match input {
+            Input::Settled { successors } => {
+                let (plan, resume) = self.pending.take().ok_or(CoreError::NoSettlementPending)?;
+                self.settled(plan, resume, successors, &mut outputs);
+            }
+            Input::SettlementFailed => {
+                let (_, resume) = self.pending.take().ok_or(CoreError::NoSettlementPending)?;
+                // the order goes back to the book, auctions and rings stop until the next round
+                if let Resume::Order(order) = resume {
+                    self.rest_order(*order, &mut outputs);
+                }
+            }
+            _ if self.pending.is_some() => return Err(CoreError::SettlementPending),
+
+            Input::SubmitOrder {
+                note,
+                transport_key,
+                block_num,
+            } => {
+                let id = note.id();
+                if let Err(reason) = self.submit(*note, transport_key, block_num, &mut outputs) {
+                    outputs.push(Output::Rejected { id, reason });
+                }
+            }
+            Input::ExpiryCheck { block_num } => {
+                self.book.update_reference_prices(&self.prices);
+                if self.config.executor.is_some()
+                    && let Some(block_num) = block_num
+                {
+                    self.drop_expired(block_num, &mut outputs);
+                    if self.config.mode == MatchingMode::Continuous {
+                        self.tasks
+                            .push_back(Task::MatchScheduled(self.scheduled_orders()));
+                        self.tasks.push_back(Task::MatchRings);
+                    }
+                }
+                if self.config.mode == MatchingMode::Continuous {
+                    self.tasks.push_back(Task::PublishDepth(None));
+                }
+            }
+            Input::BatchAuction { block_num } => {
+                if self.config.executor.is_some()
+                    && let Some(block_num) = block_num
+                {
+                    self.drop_expired(block_num, &mut outputs);
+                }
+                outputs.extend(self.held.drain(..).map(|(_, event)| Output::Event(event)));
+
+                if self.config.executor.is_some() {
+                    self.book.update_reference_prices(&self.prices);
+                    let pairs = book_pairs(&self.book).into_iter().collect();
+                    self.tasks.push_back(Task::Auctions(pairs));
+                    self.tasks.push_back(Task::DropFillOrKill);
+                    self.tasks.push_back(Task::MatchRings);
+                }
+                self.tasks.push_back(Task::PublishDepth(None));
+            }
+            // pegged orders are matched at the new price on the next expiry check
+            Input::ReferencePrices(prices) => self.prices = prices,
+            Input::ReferencePrice { pair, price } => self.prices.set(pair, price),
+            Input::QueryMarket { pair, tick } => outputs.push(Output::Market(MarketSummary::new(
+                self.public_book(),
+                &self.last_trades,
+                pair,
+                self.depth_tick(tick),
+            ))),
+            Input::QueryDepth => outputs.push(Output::Depth(depth_snapshots(
+                self.public_book(),
+                self.config.depth_tick,
+            ))),
+        }
+
+        self.run(&mut outputs);
+        Ok(outputs)
+    }
+
+    /// Validates `note` and queues its matching
+    fn submit(
+        &mut self,
+        note: Note,
+        transport_key: [u8; 32],
+        block_num: Option<BlockNumber>,
+        outputs: &mut Vec<Output>,
+    ) -> Result<(), Rejection> {
+        let root = note.script().root().to_hex();
+        if !ALLOWED_SCRIPT_ROOTS.contains(&root.as_str()) {
+            return Err(Rejection::UnknownScript(root));
+        }
+
+        let order = RestingOrder::from_note(note)?;
+        if order.note.metadata().sender() != order.creator() {
+            return Err(Rejection::NotCreator);
+        }
+        if !self.config.fee_policy.accepts(&order) {
+            return Err(Rejection::FeeTooLow);
+        }
+        if let Some(executor) = self.config.executor {
+            if !order.can_be_filled_by(executor) {
+                return Err(Rejection::OtherExecutor);
+            }
+            if let Some(block_num) = block_num {
+                self.drop_expired(block_num, outputs);
+                if order.is_expired(block_num) {
+                    return Err(Rejection::Expired);
+                }
+            }
+        }
+
+        outputs.push(Output::Accepted {
+            id: order.id(),
+            creator: order.creator(),
+            transport_key,
+        });
+
+        let pair = order.pair();
+        if self.config.mode == MatchingMode::Batch {
+            self.held
+                .push((order.id(), MarketEvent::order_accepted(&order)));
+        } else {
+            outputs.push(Output::Event(MarketEvent::order_accepted(&order)));
+        }
+
+        match (self.config.executor, self.config.mode) {
+            (None, _) => self.rest_order(order, outputs),
+            // fill-or-kill orders wait for the next auction
+            (Some(_), MatchingMode::Batch) => self.book.insert(order),
+            (Some(_), MatchingMode::Continuous) => {
+                self.book.update_reference_prices(&self.prices);
+                self.tasks.push_back(Task::Match(Box::new(order)));
+                self.tasks.push_back(Task::MatchRings);
+            }
+        }
+        // published once the order is matched and rested
+        if self.config.mode == MatchingMode::Continuous {
+            self.tasks.push_back(Task::PublishDepth(Some(pair)));
+        }
+
+        Ok(())
+    }
+
+    /// Runs the queued tasks until none is left or a settlement is needed
+    fn run(&mut self, outputs: &mut Vec<Output>) {
+        while self.pending.is_none() {
+            let Some(task) = self.tasks.pop_front() else {
+                return;
+            };
+            self.step(task, outputs);
+        }
+    }
+
+    fn step(&mut self, task: Task, outputs: &mut Vec<Output>) {
+        match task {
+            // matches the order against the book until it is filled or no counter order crosses
+            // it, the remainder is added to the book
+            Task::Match(order) => {
+                let cancelled = self.book.prevent_self_trade(&order);
+                let taker_cancelled = cancelled.iter().any(|other| other.id() == order.id());
+                for other in cancelled {
+                    self.cancel(other, CancelReason::SelfTrade, outputs);
+                }
+                if taker_cancelled {
+                    return;
+                }
+
+                let plan = if let Some(multi_match) = self.book.find_multi_match(&order) {
+                    SettlementPlan::MultiMatch(Box::new(multi_match))
+                } else if let Some(matched) = self.book.find_match(&order) {
+                    SettlementPlan::Match(Box::new(matched))
+                } else {
+                    self.rest_order(*order, outputs);
+                    return;
+                };
+                self.settle(plan, Resume::Order(order), outputs);
+            }
+
+            // scheduled orders keep their place in the book until a counter order crosses them
+            Task::MatchScheduled(mut ids) => {
+                let Some(id) = ids.pop_front() else {
+                    return;
+                };
+                self.tasks.push_front(Task::MatchScheduled(ids));
+
+                let Some(order) = self.book.get(id) else {
+                    return;
+                };
+                if self.book.find_match(order).is_none() && self.book.self_trades(order).is_empty()
+                {
+                    return;
+                }
+                if let Some(order) = self.book.remove(id) {
+                    self.tasks.push_front(Task::Match(Box::new(order)));
+                }
+            }
+
+            Task::Auctions(mut pairs) => {
+                let Some(pair) = pairs.pop_front() else {
+                    return;
+                };
+                self.tasks.push_front(Task::Auctions(pairs));
+
+                if let Some(clearing) = clearing_price(&self.book, pair) {
+                    outputs.push(Output::AuctionCleared { pair, clearing });
+                    self.tasks.push_front(Task::Auction {
+                        pair,
+                        price: clearing.price,
+                    });
+                }
+            }
+
+            // partially filled orders take part in the rest of the auction through their SWAPp'
+            Task::Auction { pair, price } => {
+                if let Some(matched) = auction_match(&self.book, pair, price) {
+                    self.settle(
+                        SettlementPlan::Match(Box::new(matched)),
+                        Resume::Auction { pair, price },
+                        outputs,
+                    );
+                }
+            }
+
+            Task::DropFillOrKill => {
+                let unfilled: Vec<NoteId> = self
+                    .book
+                    .orders()
+                    .iter()
+                    .filter(|order| order.terms().time_in_force == TimeInForce::FillOrKill)
+                    .map(RestingOrder::id)
+                    .collect();
+                for id in unfilled {
+                    if let Some(order) = self.book.remove(id) {
+                        self.rest_order(order, outputs);
+                    }
+                }
+            }
+
+            Task::MatchRings => {
+                if let Some(ring) = find_ring(&self.book) {
+                    self.settle(SettlementPlan::Ring(ring), Resume::Rings, outputs);
+                }
+            }
+
+            Task::PublishDepth(Some(pair)) => outputs.push(Output::Event(MarketEvent::Depth(
+                DepthSnapshot::coarsened(&self.book, pair, self.config.depth_tick),
+            ))),
+            Task::PublishDepth(None) => {
+                if self.config.mode == MatchingMode::Batch {
+                    self.auction_book = self.book.clone();
+                }
+                outputs.extend(
+                    depth_snapshots(&self.book, self.config.depth_tick)
+                        .into_iter()
+                        .map(|snapshot| Output::Event(MarketEvent::Depth(snapshot))),
+                );
+            }
+        }
+    }
+
+    fn settle(&mut self, plan: SettlementPlan, resume: Resume, outputs: &mut Vec<Output>) {
+        outputs.push(Output::Settle(plan.clone()));
+        self.pending = Some((plan, resume));
+    }
+
+    /// Updates the book with the outcome of a settlement and resumes matching
+    fn settled(
+        &mut self,
+        plan: SettlementPlan,
+        resume: Resume,
+        mut successors: Vec<Option<Note>>,
+        outputs: &mut Vec<Output>,
+    ) {
+        match &plan {
+            SettlementPlan::Match(matched) => match &resume {
+                Resume::Auction { pair, price } => {
+                    outputs.push(Output::Event(MarketEvent::fill_executed_at(
+                        matched, *price,
+                    )));
+                    self.last_trades.record_price(*pair, *price);
+                }
+                _ => {
+                    outputs.push(Output::Event(MarketEvent::fill_executed(matched)));
+                    self.last_trades.record(matched);
+                }
+            },
+            SettlementPlan::MultiMatch(multi_match) => {
+                for fill in &multi_match.makers {
+                    outputs.push(Output::Event(MarketEvent::order_filled(fill)));
+                    self.last_trades.record_fill(fill);
+                }
+            }
+            SettlementPlan::Ring(ring) => {
+                for fill in &ring.fills {
+                    outputs.push(Output::Event(MarketEvent::order_filled(fill)));
+                    self.last_trades.record_fill(fill);
+                }
+            }
+        }
+        for fill in plan.fills() {
+            self.book.remove(fill.order.id());
+        }
+
+        successors.resize(plan.fills().len(), None);
+        match resume {
+            Resume::Order(_) => {
+                let taker_successor = successors.pop().flatten();
+                self.rest_successors(successors);
+                if let Some(Ok(successor)) = taker_successor.map(RestingOrder::from_note) {
+                    self.tasks.push_front(Task::Match(Box::new(successor)));
+                }
+            }
+            Resume::Auction { pair, price } => {
+                self.rest_successors(successors);
+                self.tasks.push_front(Task::Auction { pair, price });
+            }
+            Resume::Rings => {
+                self.rest_successors(successors);
+                self.tasks.push_front(Task::MatchRings);
+            }
+        }
+    }
+
+    /// Adds the SWAPp' of partially filled orders to the book
+    fn rest_successors(&mut self, successors: Vec<Option<Note>>) {
+        for successor in successors.into_iter().flatten() {
+            if let Ok(successor) = RestingOrder::from_note(successor) {
+                self.book.insert(successor);
+            }
+        }
+    }
+
+    /// Adds the unfilled remainder of an order to the book, fill-or-kill orders are dropped
+    /// instead
+    fn rest_order(&mut self, order: RestingOrder, outputs: &mut Vec<Output>) {
+        if order.terms().time_in_force == TimeInForce::FillOrKill {
+            self.cancel(order, CancelReason::FillOrKill, outputs);
+            return;
+        }
+
+        self.book.insert(order);
+    }
+
+    fn drop_expired(&mut self, block_num: BlockNumber, outputs: &mut Vec<Output>) {
+        for order in self.book.remove_expired(block_num) {
+            self.cancel(order, CancelReason::Expired, outputs);
+        }
+    }
+
+    fn cancel(&mut self, order: RestingOrder, reason: CancelReason, outputs: &mut Vec<Output>) {
+        // an order cancelled before its acceptance was published leaves no public trace
+        if let Some(position) = self.held.iter().position(|(id, _)| *id == order.id()) {
+            self.held.remove(position);
+        } else {
+            outputs.push(Output::Event(MarketEvent::order_cancelled(&order)));
+        }
+        outputs.push(Output::Cancelled {
+            id: order.id(),
+            reason,
+        });
+    }
+
+    /// Orders with an execution hint, not matched on arrival before their hint allows it, and
+    /// pegged orders, which may cross once the reference price moved
+    fn scheduled_orders(&self) -> VecDeque<NoteId> {
+        self.book
+            .orders()
+            .iter()
+            .filter(|order| {
+                order.terms().pegged
+                    || !matches!(
+                        order.terms().execution_hint,
+                        NoteExecutionHint::None | NoteExecutionHint::Always
+                    )
+            })
+            .map(RestingOrder::id)
+            .collect()
+    }
+
+    /// Coarser of the `requested` tick and the configured depth tick
+    fn depth_tick(&self, requested: Option<f64>) -> Option<f64> {
+        match (requested, self.config.depth_tick) {
+            (Some(requested), Some(configured)) => Some(requested.max(configured)),
+            (requested, configured) => requested.or(configured),
+        }
+    }
+
+    /// Book the market data is computed from: the live book in continuous mode, the book of
+    /// the last auction in batch mode
+    fn public_book(&self) -> &OrderBook {
+        match self.config.mode {
+            MatchingMode::Continuous => &self.book,
+            MatchingMode::Batch => &self.auction_book,
+        }
+    }
+}
+
+/// Depth snapshot of every pair with resting orders, coarsened to `tick`
+fn depth_snapshots(book: &OrderBook, tick: Option<f64>) -> Vec<DepthSnapshot> {
+    book_pairs(book)
+        .into_iter()
+        .map(|pair| DepthSnapshot::coarsened(book, pair, tick))
+        .collect()
+}
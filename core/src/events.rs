@@ -0,0 +1,182 @@
+use crate::book::{Match, OrderBook, RestingOrder, SwapFill};
+use crate::swap_note::{Side, TradingPair};
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Asset pair of an event, faucet ids as hex strings
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PairId {
+    pub base: String,
+    pub quote: String,
+}
+
+impl From<TradingPair> for PairId {
+    fn from(pair: TradingPair) -> Self {
+        Self {
+            base: pair.base.to_hex(),
+            quote: pair.quote.to_hex(),
+        }
+    }
+}
+
+/// Market data published by the matcher
+///
+/// Events never carry note ids, accounts or exact order sizes: sizes are rounded down to a
+/// power of two by [size_bucket].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum MarketEvent {
+    /// An order was accepted into the book
+    OrderAccepted { pair: PairId, side: Side },
+
+    /// Two orders were settled at the maker price, in quote units per base unit
+    FillExecuted {
+        pair: PairId,
+        size_bucket: u64,
+        price: f64,
+    },
+
+    /// An order left the book without being filled: it expired or was a fill-or-kill order
+    OrderCancelled { pair: PairId, side: Side },
+
+    /// Resting orders of a pair aggregated by price
+    Depth(DepthSnapshot),
+}
+
+impl MarketEvent {
+    pub fn order_accepted(order: &RestingOrder) -> Self {
+        Self::OrderAccepted {
+            pair: order.pair().into(),
+            side: order.side(),
+        }
+    }
+
+    pub fn order_cancelled(order: &RestingOrder) -> Self {
+        Self::OrderCancelled {
+            pair: order.pair().into(),
+            side: order.side(),
+        }
+    }
+
+    /// Fill of the maker side of `matched`, sized in the base asset
+    pub fn fill_executed(matched: &Match) -> Self {
+        Self::order_filled(&matched.maker)
+    }
+
+    /// Fill of the maker side of `matched` at the uniform `price` of an auction, the fill
+    /// amounts only give it up to rounding
+    pub fn fill_executed_at(matched: &Match, price: f64) -> Self {
+        let (base, _) = fill_price(&matched.maker);
+
+        Self::FillExecuted {
+            pair: matched.maker.order.pair().into(),
+            size_bucket: size_bucket(base),
+            price,
+        }
+    }
+
+    /// Fill of a single order, sized in the base asset of its pair
+    pub fn order_filled(fill: &SwapFill) -> Self {
+        let (base, price) = fill_price(fill);
+
+        Self::FillExecuted {
+            pair: fill.order.pair().into(),
+            size_bucket: size_bucket(base),
+            price,
+        }
+    }
+}
+
+/// Base amount and price (quote units per base unit) of `fill`
+pub(crate) fn fill_price(fill: &SwapFill) -> (u64, f64) {
+    let (base, quote) = match fill.order.side() {
+        Side::Sell => (fill.amount_out, fill.paid()),
+        Side::Buy => (fill.paid(), fill.amount_out),
+    };
+    (base, quote as f64 / base as f64)
+}
+
+/// Orders resting at one price
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DepthLevel {
+    /// Price in quote units per base unit
+    pub price: f64,
+    /// Total base amount of the level, see [size_bucket]
+    pub size_bucket: u64,
+}
+
+/// Bids (buying the base asset) best first, asks (selling it) best first
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DepthSnapshot {
+    pub pair: PairId,
+    pub bids: Vec<DepthLevel>,
+    pub asks: Vec<DepthLevel>,
+}
+
+impl DepthSnapshot {
+    pub fn from_book(book: &OrderBook, pair: TradingPair) -> Self {
+        Self::coarsened(book, pair, None)
+    }
+
+    /// Snapshot with the level prices rounded to multiples of `tick`
+    ///
+    /// Bids are rounded down and asks up, so a coarsened level never shows a better price than
+    /// the orders it holds. A `tick` that is not a positive number is ignored.
+    pub fn coarsened(book: &OrderBook, pair: TradingPair, tick: Option<f64>) -> Self {
+        let tick = tick.filter(|tick| tick.is_finite() && *tick > 0.0);
+        let mut bids = Vec::new();
+        let mut asks = Vec::new();
+        for order in book.orders().iter().filter(|order| order.pair() == pair) {
+            match order.side() {
+                Side::Buy => bids.push(order),
+                Side::Sell => asks.push(order),
+            }
+        }
+
+        bids.sort_by(|a, b| b.price().total_cmp(&a.price()));
+        asks.sort_by(|a, b| a.price().total_cmp(&b.price()));
+
+        let round_bid = |price: f64| tick.map_or(price, |tick| (price / tick).floor() * tick);
+        let round_ask = |price: f64| tick.map_or(price, |tick| (price / tick).ceil() * tick);
+
+        Self {
+            pair: pair.into(),
+            bids: aggregate_levels(&bids, round_bid),
+            asks: aggregate_levels(&asks, round_ask),
+        }
+    }
+}
+
+/// Merges consecutive orders of the same level price, `orders` are sorted by price
+fn aggregate_levels(orders: &[&RestingOrder], level_price: impl Fn(f64) -> f64) -> Vec<DepthLevel> {
+    let mut levels: Vec<(f64, u64)> = Vec::new();
+    for order in orders {
+        let order_price = level_price(order.price());
+        match levels.last_mut() {
+            Some((price, size)) if *price == order_price => *size += order.base_amount(),
+            _ => levels.push((order_price, order.base_amount())),
+        }
+    }
+
+    // levels carry no order count, it would reveal how the size is split
+    levels
+        .into_iter()
+        .map(|(price, size)| DepthLevel {
+            price,
+            size_bucket: size_bucket(size),
+        })
+        .collect()
+}
+
+/// Rounds `amount` down to a power of two so published sizes do not identify orders
+pub fn size_bucket(amount: u64) -> u64 {
+    match amount {
+        0 => 0,
+        amount => 1 << amount.ilog2(),
+    }
+}
+
+/// Pairs with at least one order in the book
+pub fn book_pairs(book: &OrderBook) -> BTreeSet<TradingPair> {
+    book.orders().iter().map(RestingOrder::pair).collect()
+}
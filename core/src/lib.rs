@@ -0,0 +1,18 @@
+//! Deterministic core of the dark pool matcher
+//!
+//! Validates SWAPp orders, keeps the book and computes the matches without any networking,
+//! filesystem or clock access. The host drives the [engine::MatcherCore] with
+//! [engine::Input] messages and carries out the [engine::Output]s: settling the matched
+//! orders, delivering notes and publishing events.
+
+pub mod auction;
+pub mod book;
+pub mod common;
+pub mod engine;
+pub mod events;
+pub mod market;
+pub mod oracle;
+pub mod ring;
+pub mod swap_note;
+#[cfg(feature = "testing")]
+pub mod testing;
@@ -0,0 +1,75 @@
+use crate::book::{Match, OrderBook, SwapFill};
+use crate::events::{DepthSnapshot, fill_price};
+use crate::swap_note::TradingPair;
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Reference data of a pair returned by the matcher market query
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MarketSummary {
+    pub depth: DepthSnapshot,
+    /// Halfway between the best bid and the best ask levels, `None` unless both sides have
+    /// orders
+    pub midpoint: Option<f64>,
+    /// Price of the last fill of the pair settled by the matcher
+    pub last_trade: Option<f64>,
+}
+
+impl MarketSummary {
+    /// Summary of `pair`, depth levels and the midpoint are coarsened to `tick` when set, see
+    /// [DepthSnapshot::coarsened]
+    pub fn new(
+        book: &OrderBook,
+        last_trades: &LastTrades,
+        pair: TradingPair,
+        tick: Option<f64>,
+    ) -> Self {
+        let depth = DepthSnapshot::coarsened(book, pair, tick);
+        Self {
+            midpoint: midpoint(&depth),
+            depth,
+            last_trade: last_trades.get(pair),
+        }
+    }
+}
+
+/// Midpoint of the best bid and best ask levels of `depth`, so it reveals no more than the
+/// levels do
+pub fn midpoint(depth: &DepthSnapshot) -> Option<f64> {
+    let best_bid = depth.bids.first()?.price;
+    let best_ask = depth.asks.first()?.price;
+    Some((best_bid + best_ask) / 2.0)
+}
+
+/// Price of the last fill of every pair, in quote units per base unit
+#[derive(Debug, Default)]
+pub struct LastTrades {
+    prices: BTreeMap<TradingPair, f64>,
+}
+
+impl LastTrades {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the maker price of `matched` as the last trade of its pair
+    pub fn record(&mut self, matched: &Match) {
+        self.record_fill(&matched.maker);
+    }
+
+    /// Records the price of `fill` as the last trade of the pair of its order
+    pub fn record_fill(&mut self, fill: &SwapFill) {
+        let (_, price) = fill_price(fill);
+        self.record_price(fill.order.pair(), price);
+    }
+
+    /// Records `price` as the last trade of `pair`, e.g. the clearing price of an auction
+    pub fn record_price(&mut self, pair: TradingPair, price: f64) {
+        self.prices.insert(pair, price);
+    }
+
+    pub fn get(&self, pair: TradingPair) -> Option<f64> {
+        self.prices.get(&pair).copied()
+    }
+}
@@ -0,0 +1,80 @@
+use crate::swap_note::TradingPair;
+
+use miden_objects::account::AccountId;
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+/// Source of the reference prices pegged orders are matched at
+pub trait PriceOracle {
+    /// Reference price of `pair` in quote units per base unit, `None` if unknown
+    fn reference_price(&self, pair: TradingPair) -> Option<f64>;
+}
+
+#[derive(Error, Debug)]
+pub enum OracleError {
+    #[error("invalid price line `{0}`, expected `<FAUCET_A> <FAUCET_B> <PRICE>`")]
+    InvalidLine(String),
+}
+
+/// Reference prices set by hand, parsed from the content of a file or from stdin by the host
+///
+/// Every line `<FAUCET_A> <FAUCET_B> <PRICE>` prices one unit of faucet A in units of faucet B,
+/// faucet ids as hex. Empty lines and lines starting with `#` are skipped.
+#[derive(Debug, Default, Clone)]
+pub struct StaticPrices {
+    prices: BTreeMap<TradingPair, f64>,
+}
+
+impl StaticPrices {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses the lines of `text`, see [parse_price_line]
+    pub fn parse(text: &str) -> Result<Self, OracleError> {
+        let mut prices = Self::new();
+        for line in text.lines() {
+            if let Some((pair, price)) = parse_price_line(line)? {
+                prices.set(pair, price);
+            }
+        }
+        Ok(prices)
+    }
+
+    /// Sets the price of `pair` in quote units per base unit
+    pub fn set(&mut self, pair: TradingPair, price: f64) {
+        self.prices.insert(pair, price);
+    }
+}
+
+impl PriceOracle for StaticPrices {
+    fn reference_price(&self, pair: TradingPair) -> Option<f64> {
+        self.prices.get(&pair).copied()
+    }
+}
+
+/// Parses a line of [StaticPrices], returns the price in quote units per base unit of the pair
+pub fn parse_price_line(line: &str) -> Result<Option<(TradingPair, f64)>, OracleError> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    let invalid = || OracleError::InvalidLine(line.to_string());
+    let [faucet_a, faucet_b, price] = line.split_whitespace().collect::<Vec<_>>()[..] else {
+        return Err(invalid());
+    };
+    let faucet_a = AccountId::from_hex(faucet_a).map_err(|_| invalid())?;
+    let faucet_b = AccountId::from_hex(faucet_b).map_err(|_| invalid())?;
+    let price: f64 = price.parse().map_err(|_| invalid())?;
+    if !price.is_finite() || price <= 0.0 {
+        return Err(invalid());
+    }
+
+    let pair = TradingPair::new(faucet_a, faucet_b);
+    if pair.base == faucet_a {
+        Ok(Some((pair, price)))
+    } else {
+        Ok(Some((pair, 1.0 / price)))
+    }
+}
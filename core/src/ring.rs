@@ -0,0 +1,128 @@
+use crate::book::{OrderBook, RestingOrder, SwapFill};
+
+/// Largest number of orders settled together in a ring
+pub const MAX_RING_LEN: usize = 4;
+
+// rounds of shrinking the first fill before giving up on a ring
+const MAX_FILL_ROUNDS: usize = 8;
+
+/// Orders trading in a cycle of assets, settled in a single transaction by the executor
+///
+/// Every order requests the asset offered by the order before it, and the first order requests
+/// the asset offered by the last one. The executor pays the first order, then pays every order
+/// with what the previous one released. Any surplus stays with the executor, as for a
+/// [Match](crate::book::Match).
+#[derive(Debug, Clone)]
+pub struct Ring {
+    pub fills: Vec<SwapFill>,
+}
+
+/// Computes the fills of `orders` settled as a ring, with the same per-note fill math as
+/// [compute_fills](crate::book::compute_fills)
+///
+/// The first order is filled with as much as the last order can give back. When rounding or
+/// the size of an order in between leaves the executor short, the first fill is shrunk to
+/// what came back and the ring is computed again. Returns `None` if `orders` do not form a
+/// ring, do not cross, or if a fill is not accepted by its order.
+pub fn compute_ring_fills(orders: &[&RestingOrder]) -> Option<Ring> {
+    let (first, last) = (orders.first()?, orders.last()?);
+    let is_cycle = orders
+        .iter()
+        .zip(orders.iter().cycle().skip(1))
+        .all(|(order, next)| order.offered().faucet_id() == next.requested().faucet_id());
+    if orders.len() < 2 || !is_cycle {
+        return None;
+    }
+
+    let mut first_fill = first.requested().amount().min(last.offered().amount());
+    for _ in 0..MAX_FILL_ROUNDS {
+        if first_fill == 0 {
+            return None;
+        }
+
+        let mut fills = Vec::with_capacity(orders.len());
+        let mut available = first_fill;
+        for order in orders {
+            let filled_amount = available.min(order.requested().amount());
+            let amount_out = order.amount_out(filled_amount)?;
+            fills.push(SwapFill {
+                order: (*order).clone(),
+                filled_amount,
+                amount_out,
+                improvement: 0,
+            });
+            available = amount_out;
+        }
+
+        if available < first_fill {
+            first_fill = available;
+            continue;
+        }
+
+        let accepted = fills.iter().all(|fill| {
+            fill.filled_amount > 0
+                && fill.amount_out <= fill.order.offered().amount()
+                && fill.order.accepts_fill(fill.filled_amount)
+        });
+        return accepted.then_some(Ring { fills });
+    }
+
+    None
+}
+
+/// Finds a ring of 3 to [MAX_RING_LEN] live orders of the book, trying the orders in arrival
+/// order as the first order of the ring
+///
+/// Pegged orders are left out, their reference prices are per pair. A creator appears once per
+/// ring, so that no order trades against another order of its creator.
+pub fn find_ring(book: &OrderBook) -> Option<Ring> {
+    let orders: Vec<&RestingOrder> = book
+        .orders()
+        .iter()
+        .filter(|order| book.is_live(order) && !order.terms().pegged)
+        .collect();
+
+    orders.iter().find_map(|first| {
+        let mut path = vec![*first];
+        extend_ring(&orders, &mut path)
+    })
+}
+
+/// Depth first search of the rings starting with `path`
+fn extend_ring<'a>(orders: &[&'a RestingOrder], path: &mut Vec<&'a RestingOrder>) -> Option<Ring> {
+    let last = path.last()?;
+    let first = path.first()?;
+
+    if last.offered().faucet_id() == first.requested().faucet_id() {
+        return compute_ring_fills(path);
+    }
+    if path.len() == MAX_RING_LEN {
+        return None;
+    }
+
+    // every asset appears once per ring
+    let next_orders: Vec<&RestingOrder> = orders
+        .iter()
+        .filter(|next| next.requested().faucet_id() == last.offered().faucet_id())
+        .filter(|next| path.iter().all(|order| order.creator() != next.creator()))
+        .filter(|next| {
+            if next.offered().faucet_id() == first.requested().faucet_id() {
+                path.len() >= 2
+            } else {
+                path.iter()
+                    .all(|order| order.requested().faucet_id() != next.offered().faucet_id())
+            }
+        })
+        .copied()
+        .collect();
+
+    for next in next_orders {
+        path.push(next);
+        let ring = extend_ring(orders, path);
+        path.pop();
+        if ring.is_some() {
+            return ring;
+        }
+    }
+    None
+}
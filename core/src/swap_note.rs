@@ -0,0 +1,468 @@
+use crate::common::{
+    BPS_DENOMINATOR, OrderFee, OrderTerms, PEGGED_FLAG, SwapNoteError, TimeInForce,
+};
+
+use miden_lib::note::utils::build_swap_tag;
+use miden_lib::transaction::TransactionKernel;
+use miden_objects::account::AccountId;
+use miden_objects::asset::{Asset, FungibleAsset};
+use miden_objects::block::BlockNumber;
+use miden_objects::note::{
+    Note, NoteAssets, NoteExecutionHint, NoteExecutionMode, NoteInputs, NoteMetadata,
+    NoteRecipient, NoteScript, NoteTag, NoteType,
+};
+use miden_objects::{Felt, Word, ZERO};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// Number of inputs of a SWAPp note
+pub const SWAPP_NUM_INPUTS: usize = 22;
+
+const PUBLIC_SWAPP_CODE: &str = include_str!("../../notes/SWAPp.masm");
+const PRIVATE_SWAPP_CODE: &str = include_str!("../../notes/PRIVATE_SWAPp.masm");
+
+/// MAST root of the public SWAPp script, identical for both [ScriptProfile]s
+pub const PUBLIC_SWAPP_SCRIPT_ROOT: &str =
+    "0x065b3ddafe7c15cbd0fc618fbf87ce39dcc8e0bbad5323f89e4bd358905f7ff0";
+
+/// MAST root of the private SWAPp script, identical for both [ScriptProfile]s
+pub const PRIVATE_SWAPP_SCRIPT_ROOT: &str =
+    "0x789c61a3587c0244ad9ffc988fbbadc0e16884fed12d35572a300cc143c0e7f5";
+
+/// How the SWAPp scripts are assembled
+///
+/// Debug scripts keep the `debug.stack` decorators of the sources, production scripts drop them.
+/// Decorators are not part of the MAST, so both profiles share the published script roots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptProfile {
+    Debug,
+    Production,
+}
+
+impl ScriptProfile {
+    /// Profile selected at build time by the `debug-scripts` feature
+    pub const BUILD: Self = if cfg!(feature = "debug-scripts") {
+        Self::Debug
+    } else {
+        Self::Production
+    };
+}
+
+/// Returns the SWAPp script creating output notes of `note_type`, compiled once per process
+///
+/// Public orders use `SWAPp.masm`, private orders `PRIVATE_SWAPp.masm`. Both scripts share the
+/// input layout of [SwapNoteInputs]. The script is assembled with [ScriptProfile::BUILD].
+pub fn swap_note_script(note_type: NoteType) -> Result<NoteScript, SwapNoteError> {
+    static PUBLIC_SCRIPT: OnceLock<NoteScript> = OnceLock::new();
+    static PRIVATE_SCRIPT: OnceLock<NoteScript> = OnceLock::new();
+
+    let cache = match note_type {
+        NoteType::Public => &PUBLIC_SCRIPT,
+        NoteType::Private => &PRIVATE_SCRIPT,
+        NoteType::Encrypted => return Err(SwapNoteError::UnsupportedNoteType(note_type)),
+    };
+
+    if let Some(script) = cache.get() {
+        return Ok(script.clone());
+    }
+
+    let script = compile_swap_note_script(note_type, ScriptProfile::BUILD)?;
+    Ok(cache.get_or_init(|| script).clone())
+}
+
+/// Compiles the SWAPp script creating output notes of `note_type` with the given profile
+pub fn compile_swap_note_script(
+    note_type: NoteType,
+    profile: ScriptProfile,
+) -> Result<NoteScript, SwapNoteError> {
+    let code = match note_type {
+        NoteType::Public => PUBLIC_SWAPP_CODE,
+        NoteType::Private => PRIVATE_SWAPP_CODE,
+        NoteType::Encrypted => return Err(SwapNoteError::UnsupportedNoteType(note_type)),
+    };
+
+    let assembler = TransactionKernel::assembler().with_debug_mode(profile == ScriptProfile::Debug);
+    Ok(NoteScript::compile(code, assembler)?)
+}
+
+/// Inputs of a SWAPp note
+///
+/// Mirrors the `SWAPP Note Inputs` memory layout of the note scripts. The P2ID tags of the
+/// payout and of the fee are derived from their accounts when encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapNoteInputs {
+    /// Remaining amount of the requested asset
+    pub requested: FungibleAsset,
+    /// Tag of the SWAPp' notes
+    pub swapp_tag: NoteTag,
+    /// Number of fills of the order so far, 0 for the original order
+    pub fill_number: u64,
+    pub creator: AccountId,
+    pub terms: OrderTerms,
+}
+
+impl SwapNoteInputs {
+    /// Account the P2ID payout is sent to: the payout account if set, the creator otherwise
+    pub fn payout_account(&self) -> AccountId {
+        self.terms.payout.unwrap_or(self.creator)
+    }
+
+    pub fn p2id_tag(&self) -> Result<NoteTag, SwapNoteError> {
+        Ok(NoteTag::from_account_id(
+            self.payout_account(),
+            NoteExecutionMode::Local,
+        )?)
+    }
+
+    /// Tag of the P2ID note carrying the fee, `None` if the order pays no fee
+    pub fn fee_tag(&self) -> Result<Option<NoteTag>, SwapNoteError> {
+        self.terms
+            .fee
+            .map(|fee| NoteTag::from_account_id(fee.recipient, NoteExecutionMode::Local))
+            .transpose()
+            .map_err(Into::into)
+    }
+
+    /// Encodes the inputs in the order expected by the SWAPp scripts
+    pub fn encode(&self) -> Result<NoteInputs, SwapNoteError> {
+        let terms = &self.terms;
+
+        let (fee_bps, fee_recipient) = match terms.fee {
+            Some(fee) if u64::from(fee.bps) >= BPS_DENOMINATOR => {
+                return Err(SwapNoteError::InvalidFee(fee.bps.into()));
+            }
+            Some(fee) => (Felt::from(fee.bps), Some(fee.recipient)),
+            None => (ZERO, None),
+        };
+        let fee_tag = self.fee_tag()?.map_or(ZERO, Felt::from);
+        let pegged_flag = if terms.pegged { PEGGED_FLAG } else { 0 };
+
+        let requested: Word = Asset::from(self.requested).into();
+
+        Ok(NoteInputs::new(vec![
+            requested[0],
+            requested[1],
+            requested[2],
+            requested[3],
+            self.swapp_tag.into(),
+            self.p2id_tag()?.into(),
+            Felt::from(terms.expires_at.map_or(0, |block_num| block_num.as_u32())),
+            Felt::new(terms.min_fill),
+            Felt::new(self.fill_number),
+            Felt::new(terms.time_in_force.flags() | pegged_flag),
+            account_prefix(terms.executor),
+            account_suffix(terms.executor),
+            self.creator.prefix().into(),
+            self.creator.suffix(),
+            fee_bps,
+            account_prefix(fee_recipient),
+            account_suffix(fee_recipient),
+            fee_tag,
+            account_prefix(terms.payout),
+            account_suffix(terms.payout),
+            terms.aux,
+            terms.execution_hint.into(),
+        ])?)
+    }
+
+    /// Decodes the inputs of a SWAPp note, checking their number and values. The requested
+    /// amount must not be zero.
+    pub fn decode(inputs: &NoteInputs) -> Result<Self, SwapNoteError> {
+        let inputs = inputs.values();
+        if inputs.len() != SWAPP_NUM_INPUTS {
+            return Err(SwapNoteError::InvalidInputs(inputs.len()));
+        }
+
+        let requested_faucet = AccountId::try_from([inputs[3], inputs[2]])?;
+        let requested = FungibleAsset::new(requested_faucet, inputs[0].as_int())?;
+        if requested.amount() == 0 {
+            return Err(SwapNoteError::ZeroAmount);
+        }
+
+        let expires_at = match inputs[6].as_int() {
+            0 => None,
+            block_num => Some(BlockNumber::from(
+                u32::try_from(block_num).map_err(|_| SwapNoteError::InvalidExpiry(block_num))?,
+            )),
+        };
+
+        let fee = match inputs[14].as_int() {
+            0 => None,
+            bps if bps < BPS_DENOMINATOR => Some(OrderFee {
+                bps: bps as u16,
+                recipient: AccountId::try_from([inputs[15], inputs[16]])?,
+            }),
+            bps => return Err(SwapNoteError::InvalidFee(bps)),
+        };
+
+        let terms = OrderTerms {
+            expires_at,
+            min_fill: inputs[7].as_int(),
+            time_in_force: TimeInForce::from_flags(inputs[9].as_int()),
+            executor: optional_account(inputs[10], inputs[11])?,
+            fee,
+            payout: optional_account(inputs[18], inputs[19])?,
+            aux: inputs[20],
+            execution_hint: NoteExecutionHint::try_from(inputs[21].as_int())?,
+            pegged: inputs[9].as_int() & PEGGED_FLAG != 0,
+        };
+
+        Ok(Self {
+            requested,
+            swapp_tag: NoteTag::from(inputs[4].as_int() as u32),
+            fill_number: inputs[8].as_int(),
+            creator: AccountId::try_from([inputs[12], inputs[13]])?,
+            terms,
+        })
+    }
+}
+
+fn account_prefix(account_id: Option<AccountId>) -> Felt {
+    account_id.map_or(ZERO, |account_id| account_id.prefix().into())
+}
+
+fn account_suffix(account_id: Option<AccountId>) -> Felt {
+    account_id.map_or(ZERO, |account_id| account_id.suffix())
+}
+
+/// Reads an account id stored as [prefix, suffix], [0, 0] meaning no account
+fn optional_account(prefix: Felt, suffix: Felt) -> Result<Option<AccountId>, SwapNoteError> {
+    if prefix == ZERO && suffix == ZERO {
+        return Ok(None);
+    }
+    Ok(Some(AccountId::try_from([prefix, suffix])?))
+}
+
+/// Builds SWAPp notes
+///
+/// ```ignore
+/// let note = SwapNoteBuilder::new(creator, offered, requested, serial_num)
+///     .note_type(NoteType::Public)
+///     .terms(OrderTerms { min_fill: 10, ..Default::default() })
+///     .build()?;
+/// ```
+///
+/// By default the note is private, sent by the creator, not filled yet and tagged with the
+/// SWAP tag of the asset pair.
+#[derive(Debug, Clone)]
+pub struct SwapNoteBuilder {
+    creator: AccountId,
+    offered: Asset,
+    requested: Asset,
+    serial_num: Word,
+    sender: Option<AccountId>,
+    note_type: NoteType,
+    tag: Option<NoteTag>,
+    fill_number: u64,
+    terms: OrderTerms,
+}
+
+impl SwapNoteBuilder {
+    pub fn new(creator: AccountId, offered: Asset, requested: Asset, serial_num: Word) -> Self {
+        Self {
+            creator,
+            offered,
+            requested,
+            serial_num,
+            sender: None,
+            note_type: NoteType::Private,
+            tag: None,
+            fill_number: 0,
+            terms: OrderTerms::default(),
+        }
+    }
+
+    /// Sender of the note, the last consumer for a SWAPp' note
+    pub fn sender(mut self, sender: AccountId) -> Self {
+        self.sender = Some(sender);
+        self
+    }
+
+    /// Selects the script, see [swap_note_script]
+    pub fn note_type(mut self, note_type: NoteType) -> Self {
+        self.note_type = note_type;
+        self
+    }
+
+    /// Tag of the note and of its SWAPp' notes
+    pub fn tag(mut self, tag: NoteTag) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+
+    pub fn fill_number(mut self, fill_number: u64) -> Self {
+        self.fill_number = fill_number;
+        self
+    }
+
+    pub fn terms(mut self, terms: OrderTerms) -> Self {
+        self.terms = terms;
+        self
+    }
+
+    pub fn build(self) -> Result<Note, SwapNoteError> {
+        let (Asset::Fungible(offered), Asset::Fungible(requested)) = (self.offered, self.requested)
+        else {
+            return Err(SwapNoteError::InvalidAssets);
+        };
+
+        let note_script = swap_note_script(self.note_type)?;
+        let swapp_tag = match self.tag {
+            Some(tag) => tag,
+            None => swap_tag(self.note_type, offered, requested)?,
+        };
+
+        let inputs = SwapNoteInputs {
+            requested,
+            swapp_tag,
+            fill_number: self.fill_number,
+            creator: self.creator,
+            terms: self.terms,
+        }
+        .encode()?;
+
+        let metadata = NoteMetadata::new(
+            self.sender.unwrap_or(self.creator),
+            self.note_type,
+            swapp_tag,
+            self.terms.execution_hint,
+            self.terms.aux,
+        )?;
+
+        let assets = NoteAssets::new(vec![self.offered])?;
+        let recipient = NoteRecipient::new(self.serial_num, note_script, inputs);
+
+        Ok(Note::new(assets, metadata, recipient))
+    }
+}
+
+/// Generates a SWAP note tag
+/// build_swap_tag(note_type, asset1, asset2)
+/// where asset_{i} is an Asset created with AssetId of the asset pairs and 0 amount so that the tag is deterministic for a given asset pair
+fn swap_tag(
+    note_type: NoteType,
+    offered: FungibleAsset,
+    requested: FungibleAsset,
+) -> Result<NoteTag, SwapNoteError> {
+    let offered = FungibleAsset::new(offered.faucet_id(), 0)?;
+    let requested = FungibleAsset::new(requested.faucet_id(), 0)?;
+    Ok(build_swap_tag(
+        note_type,
+        &offered.into(),
+        &requested.into(),
+    )?)
+}
+
+/// Asset pair of an order, the base is the faucet with the smaller id
+///
+/// Both sides of a market map to the same pair regardless of which asset they offer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TradingPair {
+    pub base: AccountId,
+    pub quote: AccountId,
+}
+
+impl TradingPair {
+    pub fn new(faucet_a: AccountId, faucet_b: AccountId) -> Self {
+        let (base, quote) = if faucet_a <= faucet_b {
+            (faucet_a, faucet_b)
+        } else {
+            (faucet_b, faucet_a)
+        };
+        Self { base, quote }
+    }
+}
+
+/// Side of an order relative to the base asset of its [TradingPair]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Side {
+    /// Offers the quote asset for the base asset
+    Buy,
+    /// Offers the base asset for the quote asset
+    Sell,
+}
+
+/// A SWAPp order decoded from its note
+///
+/// Inverse of [SwapNoteBuilder]: `SwapOrder::try_from(&note)` rejects notes that do not run one
+/// of the SWAPp scripts, do not hold exactly one fungible asset or whose inputs do not match
+/// [SwapNoteInputs], and orders offering or requesting a zero amount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapOrder {
+    /// Remaining amount of the offered asset held by the note
+    pub offered: FungibleAsset,
+    pub note_type: NoteType,
+    pub inputs: SwapNoteInputs,
+}
+
+impl SwapOrder {
+    pub fn requested(&self) -> FungibleAsset {
+        self.inputs.requested
+    }
+
+    pub fn creator(&self) -> AccountId {
+        self.inputs.creator
+    }
+
+    pub fn terms(&self) -> &OrderTerms {
+        &self.inputs.terms
+    }
+
+    pub fn swapp_tag(&self) -> NoteTag {
+        self.inputs.swapp_tag
+    }
+
+    pub fn fill_number(&self) -> u64 {
+        self.inputs.fill_number
+    }
+
+    pub fn pair(&self) -> TradingPair {
+        TradingPair::new(self.offered.faucet_id(), self.requested().faucet_id())
+    }
+
+    pub fn side(&self) -> Side {
+        if self.offered.faucet_id() == self.pair().base {
+            Side::Sell
+        } else {
+            Side::Buy
+        }
+    }
+
+    /// Limit price in quote units per base unit
+    pub fn price(&self) -> f64 {
+        let (base, quote) = match self.side() {
+            Side::Sell => (self.offered.amount(), self.requested().amount()),
+            Side::Buy => (self.requested().amount(), self.offered.amount()),
+        };
+        quote as f64 / base as f64
+    }
+}
+
+impl TryFrom<&Note> for SwapOrder {
+    type Error = SwapNoteError;
+
+    fn try_from(note: &Note) -> Result<Self, Self::Error> {
+        let root = note.script().root();
+        let is_swap_script = [NoteType::Public, NoteType::Private]
+            .into_iter()
+            .map(swap_note_script)
+            .any(|script| script.is_ok_and(|script| script.root() == root));
+        if !is_swap_script {
+            return Err(SwapNoteError::UnknownScript);
+        }
+
+        let offered = match note.assets().iter().next() {
+            Some(Asset::Fungible(asset)) if note.assets().num_assets() == 1 => *asset,
+            _ => return Err(SwapNoteError::InvalidAssets),
+        };
+        if offered.amount() == 0 {
+            return Err(SwapNoteError::ZeroAmount);
+        }
+
+        Ok(Self {
+            offered,
+            note_type: note.metadata().note_type(),
+            inputs: SwapNoteInputs::decode(note.inputs())?,
+        })
+    }
+}
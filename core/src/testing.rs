@@ -0,0 +1,54 @@
+//! Fixtures shared by the tests of the core and of the host, see the `testing` feature
+
+use crate::book::RestingOrder;
+use crate::common::OrderTerms;
+use crate::swap_note::SwapNoteBuilder;
+
+use miden_objects::Felt;
+use miden_objects::account::AccountId;
+use miden_objects::asset::FungibleAsset;
+use miden_objects::note::Note;
+
+/// Account of one of the test account ids of miden-objects
+pub fn account(id: u128) -> AccountId {
+    AccountId::try_from(id).unwrap()
+}
+
+/// SWAPp note of `creator` offering `(faucet, amount)` for `(faucet, amount)`, `serial` makes
+/// notes of the same order distinct
+pub fn note(
+    creator: u128,
+    offered: (u128, u64),
+    requested: (u128, u64),
+    serial: u64,
+    terms: OrderTerms,
+) -> Note {
+    let offered = FungibleAsset::new(account(offered.0), offered.1).unwrap();
+    let requested = FungibleAsset::new(account(requested.0), requested.1).unwrap();
+    SwapNoteBuilder::new(
+        account(creator),
+        offered.into(),
+        requested.into(),
+        [Felt::new(serial); 4],
+    )
+    .terms(terms)
+    .build()
+    .unwrap()
+}
+
+/// Resting order of a SWAPp note with the default terms, see [note]
+pub fn order(
+    creator: u128,
+    offered: (u128, u64),
+    requested: (u128, u64),
+    serial: u64,
+) -> RestingOrder {
+    RestingOrder::from_note(note(
+        creator,
+        offered,
+        requested,
+        serial,
+        OrderTerms::default(),
+    ))
+    .unwrap()
+}
@@ -0,0 +1,390 @@
+use miden_dark_pool_core::common::{
+    OrderTerms, SwapNoteError, calculate_tokens_a_for_b, create_swap_note_successor,
+};
+use miden_dark_pool_core::engine::{
+    CancelReason, CoreError, FeePolicy, Input, MatcherConfig, MatcherCore, MatchingMode, Output,
+    Rejection, SettlementPlan,
+};
+use miden_dark_pool_core::events::{DepthSnapshot, MarketEvent};
+use miden_dark_pool_core::swap_note::TradingPair;
+use miden_dark_pool_core::testing::{account, note};
+use miden_objects::block::BlockNumber;
+use miden_objects::note::Note;
+use miden_objects::testing::account_id::{
+    ACCOUNT_ID_PRIVATE_SENDER, ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET,
+    ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1, ACCOUNT_ID_REGULAR_PRIVATE_ACCOUNT_UPDATABLE_CODE,
+    ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE,
+};
+
+const ETH: u128 = ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET;
+const BTC: u128 = ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1;
+const ALICE: u128 = ACCOUNT_ID_PRIVATE_SENDER;
+const BOB: u128 = ACCOUNT_ID_REGULAR_PRIVATE_ACCOUNT_UPDATABLE_CODE;
+const EXECUTOR: u128 = ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE;
+
+fn submit(note: &Note, block_num: u32) -> Input {
+    Input::SubmitOrder {
+        note: Box::new(note.clone()),
+        transport_key: [7; 32],
+        block_num: Some(BlockNumber::from(block_num)),
+    }
+}
+
+fn continuous() -> MatcherCore {
+    MatcherCore::new(MatcherConfig {
+        executor: Some(account(EXECUTOR)),
+        ..MatcherConfig::default()
+    })
+}
+
+/// SWAPp' of every fill of `plan`, as returned by a successful settlement
+fn successors(plan: &SettlementPlan) -> Vec<Option<Note>> {
+    plan.fills()
+        .into_iter()
+        .map(|fill| {
+            create_swap_note_successor(
+                &fill.order.note,
+                account(EXECUTOR),
+                fill.filled_amount,
+                fill.amount_out,
+            )
+            .unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn test_orders_rest_without_executor() {
+    let mut core = MatcherCore::new(MatcherConfig::default());
+    let ask = note(ALICE, (ETH, 100), (BTC, 50), 1, OrderTerms::default());
+    let bid = note(BOB, (BTC, 50), (ETH, 100), 2, OrderTerms::default());
+
+    for order in [&ask, &bid] {
+        let outputs = core.handle(submit(order, 1)).unwrap();
+        assert!(matches!(
+            &outputs[0],
+            Output::Accepted { id, transport_key: [7, ..], .. } if *id == order.id()
+        ));
+        assert!(
+            !outputs
+                .iter()
+                .any(|output| matches!(output, Output::Settle(_)))
+        );
+    }
+    assert_eq!(core.book().len(), 2);
+}
+
+#[test]
+fn test_match_settled_by_host() {
+    let mut core = continuous();
+    let maker = note(ALICE, (ETH, 100), (BTC, 50), 1, OrderTerms::default());
+    let taker = note(BOB, (BTC, 20), (ETH, 40), 2, OrderTerms::default());
+
+    core.handle(submit(&maker, 1)).unwrap();
+    let outputs = core.handle(submit(&taker, 1)).unwrap();
+
+    // matching stops at the settlement, the core waits for its outcome
+    let Some(Output::Settle(plan)) = outputs.last() else {
+        panic!("expected a settlement, got {outputs:?}");
+    };
+    let SettlementPlan::Match(matched) = plan else {
+        panic!("expected a match, got {plan:?}");
+    };
+    assert_eq!(matched.maker.order.id(), maker.id());
+    assert_eq!(matched.maker.filled_amount, 20);
+    assert_eq!(matched.taker.order.id(), taker.id());
+    assert!(core.is_settling());
+    assert_eq!(
+        core.handle(Input::QueryDepth).unwrap_err(),
+        CoreError::SettlementPending
+    );
+
+    let outputs = core
+        .handle(Input::Settled {
+            successors: successors(plan),
+        })
+        .unwrap();
+    assert!(matches!(
+        outputs[0],
+        Output::Event(MarketEvent::FillExecuted { .. })
+    ));
+    assert!(!core.is_settling());
+
+    // the SWAPp' of the maker rests in the book
+    let resting = &core.book().orders()[0];
+    assert_eq!(core.book().len(), 1);
+    assert_eq!(resting.creator(), account(ALICE));
+    assert_eq!(resting.offered().amount(), 60);
+    assert_eq!(resting.requested().amount(), 30);
+
+    assert_eq!(
+        core.handle(Input::Settled { successors: vec![] })
+            .unwrap_err(),
+        CoreError::NoSettlementPending
+    );
+}
+
+#[test]
+fn test_failed_settlement_rests_taker() {
+    let mut core = continuous();
+    let maker = note(ALICE, (ETH, 100), (BTC, 50), 1, OrderTerms::default());
+    let taker = note(BOB, (BTC, 20), (ETH, 40), 2, OrderTerms::default());
+
+    core.handle(submit(&maker, 1)).unwrap();
+    core.handle(submit(&taker, 1)).unwrap();
+    assert!(core.is_settling());
+
+    core.handle(Input::SettlementFailed).unwrap();
+    let ids: Vec<_> = core
+        .book()
+        .orders()
+        .iter()
+        .map(|order| order.id())
+        .collect();
+    assert_eq!(ids, vec![maker.id(), taker.id()]);
+}
+
+#[test]
+fn test_orders_rejected_by_core() {
+    let mut core = MatcherCore::new(MatcherConfig {
+        executor: Some(account(EXECUTOR)),
+        fee_policy: FeePolicy {
+            min_bps: 10,
+            recipient: None,
+        },
+        ..MatcherConfig::default()
+    });
+
+    let no_fee = note(ALICE, (ETH, 100), (BTC, 50), 1, OrderTerms::default());
+    let other_executor = note(
+        ALICE,
+        (ETH, 100),
+        (BTC, 50),
+        2,
+        OrderTerms {
+            executor: Some(account(BOB)),
+            ..OrderTerms::default()
+        },
+    );
+
+    let outputs = core.handle(submit(&no_fee, 1)).unwrap();
+    assert!(matches!(
+        outputs[..],
+        [Output::Rejected {
+            reason: Rejection::FeeTooLow,
+            ..
+        }]
+    ));
+
+    let mut core = continuous();
+    let outputs = core.handle(submit(&other_executor, 1)).unwrap();
+    assert!(matches!(
+        outputs[..],
+        [Output::Rejected {
+            reason: Rejection::OtherExecutor,
+            ..
+        }]
+    ));
+    assert!(core.book().is_empty());
+}
+
+#[test]
+fn test_zero_amount_orders_rejected() {
+    let mut core = continuous();
+    let maker = note(ALICE, (ETH, 100), (BTC, 50), 1, OrderTerms::default());
+    core.handle(submit(&maker, 1)).unwrap();
+
+    // would divide by zero in the fill math of the taker
+    let requests_nothing = note(BOB, (BTC, 50), (ETH, 0), 2, OrderTerms::default());
+    let offers_nothing = note(BOB, (BTC, 0), (ETH, 100), 3, OrderTerms::default());
+    for order in [&requests_nothing, &offers_nothing] {
+        let outputs = core.handle(submit(order, 1)).unwrap();
+        assert!(matches!(
+            outputs[..],
+            [Output::Rejected {
+                reason: Rejection::InvalidOrder(SwapNoteError::ZeroAmount),
+                ..
+            }]
+        ));
+    }
+    assert_eq!(core.book().len(), 1);
+    assert!(!core.is_settling());
+
+    assert_eq!(calculate_tokens_a_for_b(100, 0, 10), None);
+    assert_eq!(calculate_tokens_a_for_b(0, 100, 10), None);
+    assert_eq!(calculate_tokens_a_for_b(u64::MAX, 1, 10), None);
+    assert_eq!(calculate_tokens_a_for_b(100, 50, 10), Some(20));
+}
+
+#[test]
+fn test_expiry_check_cancels_expired_orders() {
+    let mut core = MatcherCore::new(MatcherConfig {
+        executor: Some(account(EXECUTOR)),
+        mode: MatchingMode::Batch,
+        ..MatcherConfig::default()
+    });
+    let expiring = note(
+        ALICE,
+        (ETH, 100),
+        (BTC, 50),
+        1,
+        OrderTerms {
+            expires_at: Some(BlockNumber::from(10)),
+            ..OrderTerms::default()
+        },
+    );
+    core.handle(submit(&expiring, 5)).unwrap();
+    assert_eq!(core.book().len(), 1);
+
+    let outputs = core
+        .handle(Input::ExpiryCheck {
+            block_num: Some(BlockNumber::from(11)),
+        })
+        .unwrap();
+    assert!(outputs.iter().any(|output| matches!(
+        output,
+        Output::Cancelled { id, reason: CancelReason::Expired } if *id == expiring.id()
+    )));
+    assert!(core.book().is_empty());
+}
+
+#[test]
+fn test_same_inputs_give_same_outputs() {
+    let orders = [
+        note(ALICE, (ETH, 100), (BTC, 50), 1, OrderTerms::default()),
+        note(ALICE, (ETH, 30), (BTC, 10), 2, OrderTerms::default()),
+        note(BOB, (BTC, 40), (ETH, 60), 3, OrderTerms::default()),
+    ];
+
+    let run = || {
+        let mut core = continuous();
+        let mut trace = Vec::new();
+        for order in &orders {
+            let mut input = submit(order, 1);
+            loop {
+                let outputs = core.handle(input).unwrap();
+                trace.push(format!("{outputs:?}"));
+                let Some(Output::Settle(plan)) = outputs.last() else {
+                    break;
+                };
+                input = Input::Settled {
+                    successors: successors(plan),
+                };
+            }
+        }
+        (trace, format!("{:?}", core.book().orders()))
+    };
+
+    assert_eq!(run(), run());
+}
+
+#[test]
+fn test_batch_publishes_orders_with_the_auction() {
+    let mut core = MatcherCore::new(MatcherConfig {
+        executor: Some(account(EXECUTOR)),
+        mode: MatchingMode::Batch,
+        ..MatcherConfig::default()
+    });
+    let ask = note(ALICE, (ETH, 100), (BTC, 50), 1, OrderTerms::default());
+    let bid = note(BOB, (BTC, 20), (ETH, 40), 2, OrderTerms::default());
+    let is_event = |output: &Output| matches!(output, Output::Event(_));
+
+    // arrivals and book changes stay hidden until the auction
+    for order in [&ask, &bid] {
+        let outputs = core.handle(submit(order, 1)).unwrap();
+        assert!(!outputs.iter().any(is_event));
+    }
+    let outputs = core
+        .handle(Input::ExpiryCheck {
+            block_num: Some(BlockNumber::from(2)),
+        })
+        .unwrap();
+    assert!(!outputs.iter().any(is_event));
+    assert!(matches!(
+        core.handle(Input::QueryDepth).unwrap()[..],
+        [Output::Depth(ref snapshots)] if snapshots.is_empty()
+    ));
+
+    let outputs = core
+        .handle(Input::BatchAuction {
+            block_num: Some(BlockNumber::from(2)),
+        })
+        .unwrap();
+    let accepted = outputs
+        .iter()
+        .filter(|output| matches!(output, Output::Event(MarketEvent::OrderAccepted { .. })))
+        .count();
+    assert_eq!(accepted, 2);
+    let Some(Output::Settle(plan)) = outputs.last() else {
+        panic!("expected a settlement, got {outputs:?}");
+    };
+
+    let outputs = core
+        .handle(Input::Settled {
+            successors: successors(plan),
+        })
+        .unwrap();
+    // the remainder of the ask is published with the auction
+    assert!(
+        outputs
+            .iter()
+            .any(|output| matches!(output, Output::Event(MarketEvent::Depth(_))))
+    );
+    assert!(matches!(
+        core.handle(Input::QueryDepth).unwrap()[..],
+        [Output::Depth(ref snapshots)] if snapshots.len() == 1
+    ));
+}
+
+#[test]
+fn test_depth_includes_the_order_that_rested() {
+    let mut core = continuous();
+    let ask = note(ALICE, (ETH, 100), (BTC, 50), 1, OrderTerms::default());
+
+    let outputs = core.handle(submit(&ask, 1)).unwrap();
+    let Some(Output::Event(MarketEvent::Depth(snapshot))) = outputs.last() else {
+        panic!("expected a depth snapshot, got {outputs:?}");
+    };
+    assert_eq!(snapshot.bids.len() + snapshot.asks.len(), 1);
+}
+
+#[test]
+fn test_published_depth_uses_configured_tick() {
+    let mut core = MatcherCore::new(MatcherConfig {
+        executor: Some(account(EXECUTOR)),
+        depth_tick: Some(1.0),
+        ..MatcherConfig::default()
+    });
+    let ask = note(ALICE, (ETH, 100), (BTC, 30), 1, OrderTerms::default());
+    let is_coarsened = |snapshot: &DepthSnapshot| {
+        snapshot.bids.is_empty()
+            && snapshot.asks.len() == 1
+            && snapshot.asks[0].price.fract() == 0.0
+    };
+
+    let outputs = core.handle(submit(&ask, 1)).unwrap();
+    let depth: Vec<&DepthSnapshot> = outputs
+        .iter()
+        .filter_map(|output| match output {
+            Output::Event(MarketEvent::Depth(snapshot)) => Some(snapshot),
+            _ => None,
+        })
+        .collect();
+    assert!(matches!(depth[..], [snapshot] if is_coarsened(snapshot)));
+
+    // a query cannot ask for a finer tick than the configured one
+    let outputs = core
+        .handle(Input::QueryMarket {
+            pair: TradingPair::new(account(ETH), account(BTC)),
+            tick: Some(0.001),
+        })
+        .unwrap();
+    assert!(matches!(
+        &outputs[..],
+        [Output::Market(summary)] if is_coarsened(&summary.depth)
+    ));
+    assert!(matches!(
+        &core.handle(Input::QueryDepth).unwrap()[..],
+        [Output::Depth(snapshots)] if snapshots.iter().all(is_coarsened)
+    ));
+}
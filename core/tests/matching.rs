@@ -0,0 +1,742 @@
+use miden_dark_pool_core::auction::{ClearingPrice, auction_match, clearing_price};
+use miden_dark_pool_core::book::{
+    OrderBook, RestingOrder, SelfTradePrevention, compute_multi_fills, compute_pegged_fills,
+};
+use miden_dark_pool_core::common::{OrderTerms, TimeInForce};
+use miden_dark_pool_core::events::{DepthLevel, DepthSnapshot, MarketEvent, PairId, size_bucket};
+use miden_dark_pool_core::market::{LastTrades, MarketSummary, midpoint};
+use miden_dark_pool_core::oracle::{PriceOracle, StaticPrices, parse_price_line};
+use miden_dark_pool_core::ring::{compute_ring_fills, find_ring};
+use miden_dark_pool_core::swap_note::{Side, SwapNoteBuilder, TradingPair};
+use miden_dark_pool_core::testing::{account, order};
+use miden_objects::Felt;
+use miden_objects::asset::FungibleAsset;
+use miden_objects::block::BlockNumber;
+use miden_objects::note::NoteExecutionHint;
+use miden_objects::testing::account_id::{
+    ACCOUNT_ID_PRIVATE_SENDER, ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET,
+    ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1, ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_2,
+    ACCOUNT_ID_REGULAR_PRIVATE_ACCOUNT_UPDATABLE_CODE,
+    ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE,
+};
+
+#[test]
+fn test_find_match_best_price() {
+    let eth = ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET;
+    let btc = ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1;
+
+    let mut book = OrderBook::new();
+    // 2 ETH per BTC, 2.5 ETH per BTC, and an order on the same side as the taker
+    let cheap = order(ACCOUNT_ID_PRIVATE_SENDER, (eth, 100), (btc, 50), 1);
+    let best = order(
+        ACCOUNT_ID_REGULAR_PRIVATE_ACCOUNT_UPDATABLE_CODE,
+        (eth, 100),
+        (btc, 40),
+        2,
+    );
+    let same_side = order(ACCOUNT_ID_PRIVATE_SENDER, (btc, 10), (eth, 10), 3);
+    book.insert(cheap.clone());
+    book.insert(best.clone());
+    book.insert(same_side);
+
+    // sells 30 BTC for at least 1.5 ETH each
+    let taker = order(
+        ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE,
+        (btc, 30),
+        (eth, 45),
+        4,
+    );
+    let matched = book.find_match(&taker).unwrap();
+
+    assert_eq!(matched.maker.order.id(), best.id());
+    assert_eq!(matched.maker.filled_amount, 30);
+    assert_eq!(matched.maker.amount_out, 75);
+    assert_eq!(matched.taker.filled_amount, 45);
+    assert!(matched.taker.is_complete());
+
+    // 3 ETH per BTC crosses neither maker
+    let taker = order(
+        ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE,
+        (btc, 30),
+        (eth, 90),
+        5,
+    );
+    assert!(book.find_match(&taker).is_none());
+}
+
+#[test]
+fn test_find_match_respects_min_fill() {
+    let creator = account(ACCOUNT_ID_PRIVATE_SENDER);
+    let offered = FungibleAsset::new(account(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET), 100).unwrap();
+    let requested = FungibleAsset::new(account(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1), 50).unwrap();
+    let note = SwapNoteBuilder::new(creator, offered.into(), requested.into(), [Felt::new(1); 4])
+        .terms(OrderTerms {
+            min_fill: 40,
+            ..Default::default()
+        })
+        .build()
+        .unwrap();
+    let maker = RestingOrder::from_note(note).unwrap();
+
+    let mut book = OrderBook::new();
+    book.insert(maker.clone());
+
+    // 30 BTC is below the 40 BTC minimum
+    let small = order(
+        ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE,
+        (ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1, 30),
+        (ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET, 60),
+        2,
+    );
+    assert!(book.find_match(&small).is_none());
+
+    let large = order(
+        ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE,
+        (ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1, 45),
+        (ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET, 90),
+        3,
+    );
+    let matched = book.find_match(&large).unwrap();
+    assert_eq!(matched.maker.order.id(), maker.id());
+    assert_eq!(matched.maker.filled_amount, 45);
+}
+
+#[test]
+fn test_find_match_all_or_none() {
+    let eth = ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET;
+    let btc = ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1;
+    let all_or_none = |creator: u128, offered: (u128, u64), requested: (u128, u64), serial| {
+        let creator = account(creator);
+        let offered = FungibleAsset::new(account(offered.0), offered.1).unwrap();
+        let requested = FungibleAsset::new(account(requested.0), requested.1).unwrap();
+        let note = SwapNoteBuilder::new(
+            creator,
+            offered.into(),
+            requested.into(),
+            [Felt::new(serial); 4],
+        )
+        .terms(OrderTerms {
+            time_in_force: TimeInForce::AllOrNone,
+            ..Default::default()
+        })
+        .build()
+        .unwrap();
+        RestingOrder::from_note(note).unwrap()
+    };
+
+    // 100 ETH for 50 BTC, all or none
+    let maker = all_or_none(ACCOUNT_ID_PRIVATE_SENDER, (eth, 100), (btc, 50), 1);
+    assert_eq!(maker.terms().time_in_force, TimeInForce::AllOrNone);
+    let mut book = OrderBook::new();
+    book.insert(maker.clone());
+
+    // 30 BTC would only partially fill the maker
+    let partial = order(
+        ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE,
+        (btc, 30),
+        (eth, 60),
+        2,
+    );
+    assert!(book.find_match(&partial).is_none());
+
+    // an all-or-none taker of 60 BTC for 120 ETH is never completely filled by the maker
+    let taker = all_or_none(
+        ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE,
+        (btc, 60),
+        (eth, 120),
+        3,
+    );
+    assert!(book.find_match(&taker).is_none());
+
+    // 50 BTC for 100 ETH fills both sides
+    let complete = all_or_none(
+        ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE,
+        (btc, 50),
+        (eth, 100),
+        4,
+    );
+    let matched = book.find_match(&complete).unwrap();
+    assert!(matched.maker.is_complete());
+    assert!(matched.taker.is_complete());
+}
+
+#[test]
+fn test_remove_expired_orders() {
+    let creator = account(ACCOUNT_ID_PRIVATE_SENDER);
+    let offered = FungibleAsset::new(account(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET), 100).unwrap();
+    let requested = FungibleAsset::new(account(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1), 50).unwrap();
+    let note = SwapNoteBuilder::new(creator, offered.into(), requested.into(), [Felt::new(1); 4])
+        .terms(OrderTerms {
+            expires_at: Some(BlockNumber::from(10)),
+            ..Default::default()
+        })
+        .build()
+        .unwrap();
+    let expiring = RestingOrder::from_note(note).unwrap();
+    assert_eq!(expiring.terms().expires_at, Some(BlockNumber::from(10)));
+
+    let forever = order(
+        ACCOUNT_ID_PRIVATE_SENDER,
+        (ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET, 100),
+        (ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1, 50),
+        2,
+    );
+    assert_eq!(forever.terms().expires_at, None);
+
+    let mut book = OrderBook::new();
+    book.insert(expiring.clone());
+    book.insert(forever.clone());
+
+    // a fill referencing the block before the expiry can still be included by the expiry block
+    assert!(book.remove_expired(BlockNumber::from(9)).is_empty());
+
+    let expired = book.remove_expired(BlockNumber::from(10));
+    assert_eq!(expired.len(), 1);
+    assert_eq!(expired[0].id(), expiring.id());
+    assert_eq!(book.len(), 1);
+    assert!(book.get(forever.id()).is_some());
+}
+
+#[test]
+fn test_scheduled_order_matched_after_block() {
+    let eth = ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET;
+    let btc = ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1;
+
+    let creator = account(ACCOUNT_ID_PRIVATE_SENDER);
+    let note = SwapNoteBuilder::new(
+        creator,
+        FungibleAsset::new(account(eth), 100).unwrap().into(),
+        FungibleAsset::new(account(btc), 50).unwrap().into(),
+        [Felt::new(1); 4],
+    )
+    .terms(OrderTerms {
+        aux: Felt::new(42),
+        execution_hint: NoteExecutionHint::after_block(BlockNumber::from(20)).unwrap(),
+        ..Default::default()
+    })
+    .build()
+    .unwrap();
+    let scheduled = RestingOrder::from_note(note).unwrap();
+    assert_eq!(scheduled.terms().aux, Felt::new(42));
+    assert_eq!(scheduled.note.metadata().aux(), Felt::new(42));
+
+    let mut book = OrderBook::new();
+    book.insert(scheduled.clone());
+    let taker = order(
+        ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE,
+        (btc, 50),
+        (eth, 100),
+        2,
+    );
+
+    book.remove_expired(BlockNumber::from(19));
+    assert!(book.find_match(&taker).is_none());
+
+    book.remove_expired(BlockNumber::from(20));
+    let matched = book.find_match(&taker).unwrap();
+    assert_eq!(matched.maker.order.id(), scheduled.id());
+}
+
+#[test]
+fn test_depth_snapshot_aggregates_levels() {
+    let eth = account(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET);
+    let btc = account(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1);
+    let pair = TradingPair::new(eth, btc);
+    let (base, quote) = (pair.base.into(), pair.quote.into());
+
+    let mut book = OrderBook::new();
+    // two asks at 2 quote per base, one at 3, one bid at 1
+    book.insert(order(ACCOUNT_ID_PRIVATE_SENDER, (base, 10), (quote, 20), 1));
+    book.insert(order(ACCOUNT_ID_PRIVATE_SENDER, (base, 7), (quote, 14), 2));
+    book.insert(order(ACCOUNT_ID_PRIVATE_SENDER, (base, 5), (quote, 15), 3));
+    book.insert(order(ACCOUNT_ID_PRIVATE_SENDER, (quote, 9), (base, 9), 4));
+
+    let snapshot = DepthSnapshot::from_book(&book, pair);
+    assert_eq!(snapshot.pair, PairId::from(pair));
+    assert_eq!(
+        snapshot.asks,
+        vec![
+            DepthLevel {
+                price: 2.0,
+                size_bucket: 16
+            },
+            DepthLevel {
+                price: 3.0,
+                size_bucket: 4
+            },
+        ]
+    );
+    assert_eq!(
+        snapshot.bids,
+        vec![DepthLevel {
+            price: 1.0,
+            size_bucket: 8
+        }]
+    );
+
+    assert_eq!(size_bucket(0), 0);
+    assert_eq!(size_bucket(1), 1);
+    assert_eq!(size_bucket(1023), 512);
+    assert_eq!(size_bucket(1024), 1024);
+}
+
+#[test]
+fn test_market_summary() {
+    let eth = account(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET);
+    let btc = account(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1);
+    let pair = TradingPair::new(eth, btc);
+    let (base, quote) = (pair.base.into(), pair.quote.into());
+
+    let mut book = OrderBook::new();
+    let mut last_trades = LastTrades::new();
+    // asks at 2.5 and 2.75, bids at 1.5 and 1.25 quote per base
+    book.insert(order(ACCOUNT_ID_PRIVATE_SENDER, (base, 4), (quote, 10), 1));
+    book.insert(order(ACCOUNT_ID_PRIVATE_SENDER, (base, 4), (quote, 11), 2));
+    book.insert(order(ACCOUNT_ID_PRIVATE_SENDER, (quote, 6), (base, 4), 3));
+    book.insert(order(ACCOUNT_ID_PRIVATE_SENDER, (quote, 5), (base, 4), 4));
+
+    let summary = MarketSummary::new(&book, &last_trades, pair, Some(1.0));
+    assert_eq!(summary.midpoint, Some(2.0));
+    assert_eq!(summary.last_trade, None);
+    // coarsened levels never show a better price than their orders
+    assert_eq!(
+        summary.depth.asks,
+        vec![DepthLevel {
+            price: 3.0,
+            size_bucket: 8
+        }]
+    );
+    assert_eq!(
+        summary.depth.bids,
+        vec![DepthLevel {
+            price: 1.0,
+            size_bucket: 8
+        }]
+    );
+    assert_eq!(
+        midpoint(&DepthSnapshot::from_book(&book, TradingPair::new(eth, eth))),
+        None
+    );
+    // the midpoint is taken between the coarsened levels, not the exact prices
+    let summary = MarketSummary::new(&book, &last_trades, pair, Some(0.75));
+    assert_eq!(summary.midpoint, Some(2.25));
+
+    // sells 4 base for at least 1 quote each, filled at the best bid
+    let taker = order(
+        ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE,
+        (base, 4),
+        (quote, 4),
+        5,
+    );
+    let matched = book.find_match(&taker).unwrap();
+    last_trades.record(&matched);
+    assert_eq!(last_trades.get(pair), Some(1.5));
+
+    let summary = MarketSummary::new(&book, &last_trades, pair, None);
+    assert_eq!(summary.last_trade, Some(1.5));
+    assert_eq!(summary.depth.asks.len(), 2);
+    assert_eq!(summary.depth.bids.len(), 2);
+}
+
+#[test]
+fn test_pegged_order_matched_at_reference_price() {
+    let eth = account(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET);
+    let btc = account(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1);
+    let pair = TradingPair::new(eth, btc);
+    let (base, quote) = (pair.base, pair.quote);
+
+    // sells 10 base for at least 2 quote each, pegged to the reference price
+    let note = SwapNoteBuilder::new(
+        account(ACCOUNT_ID_PRIVATE_SENDER),
+        FungibleAsset::new(base, 10).unwrap().into(),
+        FungibleAsset::new(quote, 20).unwrap().into(),
+        [Felt::new(1); 4],
+    )
+    .terms(OrderTerms {
+        pegged: true,
+        ..Default::default()
+    })
+    .build()
+    .unwrap();
+    let pegged = RestingOrder::from_note(note).unwrap();
+    assert!(pegged.terms().pegged);
+
+    let mut book = OrderBook::new();
+    book.insert(pegged.clone());
+
+    // buys 10 base for at most 3 quote each
+    let taker = order(
+        ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE,
+        (quote.into(), 30),
+        (base.into(), 10),
+        2,
+    );
+
+    // no reference price
+    assert!(book.find_match(&taker).is_none());
+
+    // the reference is below the limit of the pegged order
+    let mut prices = StaticPrices::new();
+    prices.set(pair, 1.5);
+    book.update_reference_prices(&prices);
+    assert!(book.find_match(&taker).is_none());
+
+    // the reference is above the limit of the taker
+    prices.set(pair, 3.5);
+    book.update_reference_prices(&prices);
+    assert!(book.find_match(&taker).is_none());
+
+    prices.set(pair, 2.5);
+    book.update_reference_prices(&prices);
+    assert_eq!(book.reference_price(pair), Some(2.5));
+    let matched = book.find_match(&taker).unwrap();
+    assert_eq!(matched.maker.order.id(), pegged.id());
+    assert_eq!(matched.maker.filled_amount, 20);
+    assert!(matched.maker.is_complete());
+
+    // both sides trade at the reference price, the executor keeps no spread
+    assert_eq!(matched.maker.amount_out, matched.taker.paid());
+    assert_eq!(matched.taker.amount_out, matched.maker.paid());
+    assert!(matched.maker.improvement > 0 && matched.taker.improvement > 0);
+}
+
+#[test]
+fn test_pegged_fills_at_reference_price() {
+    let eth = account(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET);
+    let btc = account(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1);
+    let pair = TradingPair::new(eth, btc);
+    let (base, quote) = (pair.base, pair.quote);
+
+    // sells 1000 base for at least 2 quote each, buys 600 base for at most 3 quote each
+    let seller = order(
+        ACCOUNT_ID_PRIVATE_SENDER,
+        (base.into(), 1000),
+        (quote.into(), 2000),
+        1,
+    );
+    let buyer = order(
+        ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE,
+        (quote.into(), 1800),
+        (base.into(), 600),
+        2,
+    );
+
+    for (maker, taker) in [(&seller, &buyer), (&buyer, &seller)] {
+        let matched = compute_pegged_fills(maker, taker, 2.5).unwrap();
+        let (sold, bought) = match maker.side() {
+            Side::Sell => (&matched.maker, &matched.taker),
+            Side::Buy => (&matched.taker, &matched.maker),
+        };
+
+        // the buyer receives all the base released by the seller, and the other way around
+        assert_eq!(bought.paid(), sold.amount_out);
+        assert_eq!(sold.paid(), bought.amount_out);
+        assert_eq!(sold.amount_out, 600);
+
+        // both are filled at the reference price, up to one unit of the buyer's limit price
+        let price = sold.paid() as f64 / sold.amount_out as f64;
+        assert!(price <= 2.5 && price > 2.5 - 3.0 / 600.0, "{price}");
+        assert!(sold.paid() > sold.filled_amount);
+        assert!(bought.paid() > bought.filled_amount);
+    }
+
+    // the buyer cannot pay more than its limit
+    assert!(compute_pegged_fills(&seller, &buyer, 3.5).is_none());
+}
+
+#[test]
+fn test_price_feed_lines() {
+    let eth = account(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET);
+    let btc = account(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1);
+    let pair = TradingPair::new(eth, btc);
+
+    let feed = format!(
+        "# reference prices\n\n{} {} 4\n",
+        pair.quote.to_hex(),
+        pair.base.to_hex()
+    );
+    let prices = StaticPrices::parse(&feed).unwrap();
+    // 4 base per quote is 0.25 quote per base
+    assert_eq!(prices.reference_price(pair), Some(0.25));
+
+    assert!(parse_price_line("not a price").is_err());
+    assert!(parse_price_line(&format!("{} {} -1", eth.to_hex(), btc.to_hex())).is_err());
+    assert!(parse_price_line("   ").unwrap().is_none());
+}
+
+#[test]
+fn test_batch_auction_clearing_price() {
+    let eth = account(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET);
+    let btc = account(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1);
+    let pair = TradingPair::new(eth, btc);
+    let (base, quote) = (pair.base.into(), pair.quote.into());
+
+    let mut book = OrderBook::new();
+    // asks of 100 base at 2 and at 4 quote per base
+    let cheap_ask = order(ACCOUNT_ID_PRIVATE_SENDER, (base, 100), (quote, 200), 1);
+    book.insert(cheap_ask.clone());
+    book.insert(order(
+        ACCOUNT_ID_PRIVATE_SENDER,
+        (base, 100),
+        (quote, 400),
+        2,
+    ));
+    assert_eq!(clearing_price(&book, pair), None);
+
+    // bids of 100 base at 3 and of 50 base at 5 quote per base
+    let bid = order(
+        ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE,
+        (quote, 300),
+        (base, 100),
+        3,
+    );
+    let best_bid = order(
+        ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE,
+        (quote, 250),
+        (base, 50),
+        4,
+    );
+    book.insert(bid);
+    book.insert(best_bid.clone());
+
+    // 100 base clear anywhere between 2 and 3
+    let clearing = clearing_price(&book, pair).unwrap();
+    assert_eq!(
+        clearing,
+        ClearingPrice {
+            price: 2.5,
+            volume: 100
+        }
+    );
+
+    let matched = auction_match(&book, pair, clearing.price).unwrap();
+    assert_eq!(matched.maker.order.id(), cheap_ask.id());
+    assert_eq!(matched.taker.order.id(), best_bid.id());
+    // both sides trade 50 base for 125 quote at the clearing price, not at their own price
+    assert_eq!(matched.maker.amount_out, 50);
+    assert_eq!(matched.maker.paid(), 125);
+    assert_eq!(matched.taker.amount_out, 125);
+    assert_eq!(matched.taker.paid(), 50);
+    // each note is filled at its own price, the executor pays the rest as an improvement
+    assert_eq!(
+        (matched.maker.filled_amount, matched.maker.improvement),
+        (100, 25)
+    );
+    assert_eq!(
+        (matched.taker.filled_amount, matched.taker.improvement),
+        (25, 25)
+    );
+    assert_eq!(
+        MarketEvent::fill_executed_at(&matched, clearing.price),
+        MarketEvent::FillExecuted {
+            pair: pair.into(),
+            size_bucket: size_bucket(50),
+            price: 2.5,
+        }
+    );
+
+    // nothing accepts a price above every ask and bid
+    assert!(auction_match(&book, pair, 10.0).is_none());
+}
+
+#[test]
+fn test_find_ring_of_three_orders() {
+    let eth = ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET;
+    let btc = ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1;
+    let usdc = ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_2;
+
+    // ETH -> BTC, BTC -> USDC and USDC -> ETH, no two orders trade the same pair
+    let eth_btc = order(ACCOUNT_ID_PRIVATE_SENDER, (eth, 100), (btc, 50), 1);
+    let btc_usdc = order(
+        ACCOUNT_ID_REGULAR_PRIVATE_ACCOUNT_UPDATABLE_CODE,
+        (btc, 60),
+        (usdc, 30),
+        2,
+    );
+    let usdc_eth = order(
+        ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE,
+        (usdc, 40),
+        (eth, 70),
+        3,
+    );
+
+    let mut book = OrderBook::new();
+    book.insert(eth_btc.clone());
+    book.insert(btc_usdc.clone());
+    assert!(find_ring(&book).is_none());
+    book.insert(usdc_eth.clone());
+    assert!(book.find_match(&usdc_eth).is_none());
+
+    let ring = find_ring(&book).unwrap();
+    let ids: Vec<_> = ring.fills.iter().map(|fill| fill.order.id()).collect();
+    assert_eq!(ids, vec![eth_btc.id(), usdc_eth.id(), btc_usdc.id()]);
+
+    let amounts: Vec<_> = ring
+        .fills
+        .iter()
+        .map(|fill| (fill.filled_amount, fill.amount_out))
+        .collect();
+    assert_eq!(amounts, vec![(50, 100), (70, 40), (30, 60)]);
+    assert!(ring.fills.iter().all(|fill| fill.is_complete()));
+
+    // 1.25 USDC per BTC leaves the executor short of BTC whatever the first fill
+    let expensive = order(
+        ACCOUNT_ID_REGULAR_PRIVATE_ACCOUNT_UPDATABLE_CODE,
+        (btc, 40),
+        (usdc, 50),
+        4,
+    );
+    assert!(compute_ring_fills(&[&eth_btc, &usdc_eth, &expensive]).is_none());
+    // the orders of a ring must form a cycle
+    assert!(compute_ring_fills(&[&eth_btc, &btc_usdc, &usdc_eth]).is_none());
+}
+
+#[test]
+fn test_find_multi_match() {
+    let eth = ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET;
+    let btc = ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1;
+
+    // two makers at 2 ETH per BTC, none large enough for the taker alone
+    let first = order(ACCOUNT_ID_PRIVATE_SENDER, (eth, 40), (btc, 20), 1);
+    let second = order(
+        ACCOUNT_ID_REGULAR_PRIVATE_ACCOUNT_UPDATABLE_CODE,
+        (eth, 30),
+        (btc, 15),
+        2,
+    );
+    let mut book = OrderBook::new();
+    book.insert(first.clone());
+
+    // sells 30 BTC for at least 1.5 ETH each
+    let taker = order(
+        ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE,
+        (btc, 30),
+        (eth, 45),
+        3,
+    );
+    assert!(book.find_multi_match(&taker).is_none());
+
+    book.insert(second.clone());
+    let multi_match = book.find_multi_match(&taker).unwrap();
+
+    let makers: Vec<_> = multi_match
+        .makers
+        .iter()
+        .map(|fill| (fill.order.id(), fill.filled_amount, fill.amount_out))
+        .collect();
+    assert_eq!(makers, vec![(first.id(), 20, 40), (second.id(), 10, 20)]);
+    assert_eq!(multi_match.taker.filled_amount, 45);
+    assert_eq!(multi_match.taker.amount_out, 30);
+    assert!(multi_match.taker.is_complete());
+    assert!(!multi_match.makers[1].is_complete());
+
+    // a single maker filling the taker is left to find_match
+    let small_taker = order(
+        ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE,
+        (btc, 10),
+        (eth, 15),
+        4,
+    );
+    assert!(book.find_multi_match(&small_taker).is_none());
+    assert!(book.find_match(&small_taker).is_some());
+    assert!(compute_multi_fills(&[&first], &small_taker).is_some());
+}
+
+#[test]
+fn test_self_trade_prevention() {
+    let eth = ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET;
+    let btc = ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1;
+
+    // alice rests the best ask at 2.5 ETH per BTC, bob an ask at 2 ETH per BTC
+    let alice = ACCOUNT_ID_PRIVATE_SENDER;
+    let own_ask = order(alice, (eth, 100), (btc, 40), 1);
+    let other_ask = order(
+        ACCOUNT_ID_REGULAR_PRIVATE_ACCOUNT_UPDATABLE_CODE,
+        (eth, 100),
+        (btc, 50),
+        2,
+    );
+    // alice then sells 30 BTC for at least 1.5 ETH each
+    let own_bid = order(alice, (btc, 30), (eth, 45), 3);
+
+    let book_with = |policy| {
+        let mut book = OrderBook::with_self_trade_prevention(policy);
+        book.insert(own_ask.clone());
+        book.insert(other_ask.clone());
+        book
+    };
+
+    // whatever the policy, alice is only matched against bob
+    let book = book_with(SelfTradePrevention::Skip);
+    let self_trades: Vec<_> = book.self_trades(&own_bid).iter().map(|o| o.id()).collect();
+    assert_eq!(self_trades, vec![own_ask.id()]);
+    assert_eq!(
+        book.find_match(&own_bid).unwrap().maker.order.id(),
+        other_ask.id()
+    );
+
+    // skip leaves both orders resting
+    let mut book = book_with(SelfTradePrevention::Skip);
+    assert!(book.prevent_self_trade(&own_bid).is_empty());
+    assert_eq!(book.len(), 2);
+
+    // cancel newest cancels the incoming order
+    let mut book = book_with(SelfTradePrevention::CancelNewest);
+    let cancelled: Vec<_> = book
+        .prevent_self_trade(&own_bid)
+        .iter()
+        .map(RestingOrder::id)
+        .collect();
+    assert_eq!(cancelled, vec![own_bid.id()]);
+    assert!(book.get(own_ask.id()).is_some());
+
+    // cancel oldest removes the resting order of alice, the bid then matches bob
+    let mut book = book_with(SelfTradePrevention::CancelOldest);
+    let cancelled: Vec<_> = book
+        .prevent_self_trade(&own_bid)
+        .iter()
+        .map(RestingOrder::id)
+        .collect();
+    assert_eq!(cancelled, vec![own_ask.id()]);
+    assert!(book.get(own_ask.id()).is_none());
+    assert!(book.self_trades(&own_bid).is_empty());
+    assert_eq!(
+        book.find_match(&own_bid).unwrap().maker.order.id(),
+        other_ask.id()
+    );
+
+    // an own order priced behind another creator's order does not trigger the policy
+    let mut book = OrderBook::with_self_trade_prevention(SelfTradePrevention::CancelNewest);
+    let worse_own_ask = order(alice, (eth, 100), (btc, 60), 4);
+    book.insert(other_ask.clone());
+    book.insert(worse_own_ask);
+    assert!(book.self_trades(&own_bid).is_empty());
+    assert!(book.prevent_self_trade(&own_bid).is_empty());
+}
+
+#[test]
+fn test_self_trades_left_out_of_auctions_and_rings() {
+    let eth = ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET;
+    let btc = ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1;
+    let usdc = ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_2;
+    let alice = ACCOUNT_ID_PRIVATE_SENDER;
+
+    let mut book = OrderBook::new();
+    book.insert(order(alice, (btc, 10), (eth, 20), 1));
+    book.insert(order(alice, (eth, 20), (btc, 10), 2));
+    let pair = TradingPair::new(account(eth), account(btc));
+    let clearing = clearing_price(&book, pair).unwrap();
+    assert!(auction_match(&book, pair, clearing.price).is_none());
+
+    // alice would trade ETH -> BTC and USDC -> ETH around bob's BTC -> USDC
+    let mut book = OrderBook::new();
+    book.insert(order(alice, (btc, 10), (eth, 20), 1));
+    book.insert(order(
+        ACCOUNT_ID_REGULAR_PRIVATE_ACCOUNT_UPDATABLE_CODE,
+        (usdc, 100),
+        (btc, 10),
+        2,
+    ));
+    book.insert(order(alice, (eth, 20), (usdc, 100), 3));
+    assert!(find_ring(&book).is_none());
+}
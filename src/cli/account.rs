@@ -0,0 +1,190 @@
+use crate::utils::common::{AccountSetupError, KEYSTORE_PATH, client_setup, create_wallet};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use miden_client::account::{Account, AccountId, AccountStorageMode};
+use miden_client::keystore::{FilesystemKeyStore, KeyStoreError};
+use miden_client::{Client, ClientError};
+use miden_objects::AccountIdError;
+use miden_objects::account::AccountFile;
+use miden_objects::asset::Asset;
+use std::path::PathBuf;
+use thiserror::Error;
+
+#[derive(Subcommand, Debug)]
+#[command(about = "Manages the accounts used to trade on the dark pool")]
+pub enum AccountCommand {
+    #[command(name = "new")]
+    New(NewAccount),
+
+    #[command(name = "import")]
+    Import(ImportAccount),
+
+    #[command(name = "list")]
+    List(ListAccounts),
+
+    #[command(name = "balance")]
+    Balance(AccountBalance),
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum StorageMode {
+    Public,
+    Private,
+}
+
+impl From<StorageMode> for AccountStorageMode {
+    fn from(mode: StorageMode) -> Self {
+        match mode {
+            StorageMode::Public => AccountStorageMode::Public,
+            StorageMode::Private => AccountStorageMode::Private,
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+#[command(about = "Creates a new wallet and stores its key in the keystore")]
+pub struct NewAccount {
+    /// Whether the account state is stored on chain
+    #[arg(long, value_enum, default_value_t = StorageMode::Public)]
+    storage_mode: StorageMode,
+}
+
+#[derive(Parser, Debug)]
+#[command(about = "Imports an existing account")]
+pub struct ImportAccount {
+    /// Account file (account, seed and secret key), required for private accounts
+    #[arg(
+        long,
+        conflicts_with = "account_id",
+        required_unless_present = "account_id"
+    )]
+    account_file: Option<PathBuf>,
+
+    /// Id of a public account, its key must already be in the keystore to sign with it
+    #[arg(long)]
+    account_id: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+#[command(about = "Lists the accounts tracked by the local store")]
+pub struct ListAccounts {}
+
+#[derive(Parser, Debug)]
+#[command(about = "Shows the fungible assets held by an account")]
+pub struct AccountBalance {
+    /// Unique user identifier
+    #[arg(long)]
+    user_id: String,
+}
+
+#[derive(Error, Debug)]
+pub enum AccountError {
+    #[error("client error: {0}")]
+    Client(#[from] ClientError),
+
+    #[error("invalid account id: {0}")]
+    InvalidAccountID(#[from] AccountIdError),
+
+    #[error("account setup error: {0}")]
+    AccountSetup(#[from] AccountSetupError),
+
+    #[error("keystore error: {0}")]
+    KeyStore(#[from] KeyStoreError),
+
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("account {0} not found")]
+    AccountNotFound(AccountId),
+}
+
+impl AccountCommand {
+    pub async fn run(&self) -> Result<(), AccountError> {
+        let mut client = client_setup().await?;
+
+        match self {
+            AccountCommand::New(cmd) => {
+                let keystore = FilesystemKeyStore::new(KEYSTORE_PATH.into())?;
+                let account =
+                    create_wallet(&mut client, &keystore, cmd.storage_mode.into()).await?;
+                client.sync_state().await?;
+
+                println!("Account created: {}", account.id().to_hex());
+            }
+
+            AccountCommand::Import(cmd) => {
+                let account_id = match (&cmd.account_file, &cmd.account_id) {
+                    (Some(path), _) => {
+                        let account_file = AccountFile::read(path)?;
+                        let keystore = FilesystemKeyStore::new(KEYSTORE_PATH.into())?;
+                        keystore.add_key(&account_file.auth_secret_key)?;
+
+                        let account = account_file.account;
+                        client
+                            .add_account(&account, account_file.account_seed, false)
+                            .await?;
+                        account.id()
+                    }
+                    (None, Some(account_id)) => {
+                        let account_id = AccountId::from_hex(account_id)?;
+                        client.import_account_by_id(account_id).await?;
+                        account_id
+                    }
+                    (None, None) => unreachable!("clap requires one of the two arguments"),
+                };
+                client.sync_state().await?;
+
+                println!("Account imported: {}", account_id.to_hex());
+            }
+
+            AccountCommand::List(_) => {
+                for (header, status) in client.get_account_headers().await? {
+                    let id = header.id();
+                    println!(
+                        "{} {:?} {:?} nonce={} status={:?}",
+                        id.to_hex(),
+                        id.account_type(),
+                        id.storage_mode(),
+                        header.nonce(),
+                        status
+                    );
+                }
+            }
+
+            AccountCommand::Balance(cmd) => {
+                let account_id = AccountId::from_hex(&cmd.user_id)?;
+                let account = tracked_account(&mut client, account_id).await?;
+
+                for asset in account.vault().assets() {
+                    match asset {
+                        Asset::Fungible(asset) => {
+                            println!("{}: {}", asset.faucet_id().to_hex(), asset.amount())
+                        }
+                        Asset::NonFungible(asset) => {
+                            println!("{}: non fungible", asset.faucet_id_prefix())
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the account from the local store, importing it from the network if not tracked yet
+async fn tracked_account(
+    client: &mut Client,
+    account_id: AccountId,
+) -> Result<Account, AccountError> {
+    if client.get_account(account_id).await?.is_none() {
+        client.import_account_by_id(account_id).await?;
+    }
+
+    let record = client
+        .get_account(account_id)
+        .await?
+        .ok_or(AccountError::AccountNotFound(account_id))?;
+
+    Ok(record.account().clone())
+}
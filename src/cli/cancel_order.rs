@@ -23,7 +23,7 @@ pub struct CancelOrder {
 }
 
 impl CancelOrder {
-    pub(crate) async fn run(&self) -> Result<bool, OrderError> {
+    pub async fn run(&self) -> Result<bool, OrderError> {
         let mut client = client_setup().await?;
 
         let account_id = AccountId::from_hex(&self.user_id)?;
@@ -16,7 +16,7 @@ pub struct ConsumeSwapped {
 }
 
 impl ConsumeSwapped {
-    pub(crate) async fn run(&self) -> Result<bool, OrderError> {
+    pub async fn run(&self) -> Result<bool, OrderError> {
         let mut client = client_setup().await?;
 
         let user_id = AccountId::from_hex(&self.user_id)?;
@@ -30,7 +30,7 @@ impl ConsumeSwapped {
             let list_of_note_ids: Vec<_> =
                 consumable_notes.iter().map(|(note, _)| note.id()).collect();
 
-            if list_of_note_ids.len() > 0 {
+            if !list_of_note_ids.is_empty() {
                 let transaction_request =
                     TransactionRequestBuilder::consume_notes(list_of_note_ids)
                         .build()
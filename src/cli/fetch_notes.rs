@@ -0,0 +1,113 @@
+use crate::utils::common::{client_setup, get_account};
+use crate::utils::protocol::{
+    MATCHER_ADDRESS, MatcherRequest, MatcherResponse, ProtocolError, read_message, write_message,
+};
+use crate::utils::transport::{TRANSPORT_KEYS_DIR, TransportError, TransportKey};
+
+use clap::Parser;
+use miden_client::ClientError;
+use miden_client::account::AccountId;
+use miden_client::note::NoteFile;
+use miden_objects::AccountIdError;
+use miden_objects::utils::Deserializable;
+use thiserror::Error;
+use tokio::net::TcpStream;
+
+#[derive(Parser, Debug)]
+#[command(about = "Fetches the private notes delivered by the matcher and imports them")]
+pub struct FetchNotes {
+    /// Unique user identifier
+    #[arg(long)]
+    user_id: String,
+}
+
+#[derive(Error, Debug)]
+pub enum FetchNotesError {
+    #[error("client error: {0}")]
+    Client(#[from] ClientError),
+
+    #[error("invalid account id: {0}")]
+    InvalidAccountID(#[from] AccountIdError),
+
+    #[error("matcher communication error: {0}")]
+    Protocol(#[from] ProtocolError),
+
+    #[error("transport error: {0}")]
+    Transport(#[from] TransportError),
+}
+
+impl FetchNotes {
+    pub async fn run(&self) -> Result<usize, FetchNotesError> {
+        let mut client = client_setup().await?;
+
+        let user_id = AccountId::from_hex(&self.user_id)?;
+        let user = get_account(&mut client, user_id).await?;
+        let transport_key = TransportKey::load_or_create(TRANSPORT_KEYS_DIR, user.id())?;
+
+        let mut stream = TcpStream::connect(MATCHER_ADDRESS)
+            .await
+            .map_err(|e| FetchNotesError::Protocol(e.into()))?;
+        let request = MatcherRequest::FetchNotes {
+            account_id: user.id().to_hex(),
+        };
+        write_message(&mut stream, &request).await?;
+
+        // the matcher only hands out the notes once the transport key is proven
+        let sealed_notes = match read_message(&mut stream).await? {
+            MatcherResponse::KeyChallenge(challenge) => {
+                let secret = transport_key.open(&challenge)?;
+                write_message(&mut stream, &MatcherRequest::AnswerChallenge { secret }).await?;
+                match read_message(&mut stream).await? {
+                    MatcherResponse::Notes(sealed_notes) => sealed_notes,
+                    _ => return Err(ProtocolError::UnexpectedResponse.into()),
+                }
+            }
+            MatcherResponse::Notes(_) => return Ok(0),
+            _ => return Err(ProtocolError::UnexpectedResponse.into()),
+        };
+
+        // only the processed notes are acknowledged, the matcher keeps the others for the next
+        // fetch if one of them fails
+        let mut processed = Vec::new();
+        let mut imported = 0;
+        let mut result: Result<(), FetchNotesError> = Ok(());
+        for sealed_note in sealed_notes {
+            let payload = match transport_key.open(&sealed_note) {
+                Ok(payload) => payload,
+                Err(e) => {
+                    result = Err(e.into());
+                    break;
+                }
+            };
+            let note_file = match NoteFile::read_from_bytes(&payload) {
+                Ok(note_file) => note_file,
+                Err(e) => {
+                    eprintln!("Skipping malformed note: {}", e);
+                    processed.push(sealed_note.ephemeral_key);
+                    continue;
+                }
+            };
+
+            match client.import_note(note_file).await {
+                Ok(note_id) => {
+                    println!("Imported note: {}", note_id.to_hex());
+                    processed.push(sealed_note.ephemeral_key);
+                    imported += 1;
+                }
+                Err(e) => {
+                    result = Err(e.into());
+                    break;
+                }
+            }
+        }
+
+        let ack = MatcherRequest::AckNotes {
+            ephemeral_keys: processed,
+        };
+        write_message(&mut stream, &ack).await?;
+        result?;
+        client.sync_state().await?;
+
+        Ok(imported)
+    }
+}
@@ -0,0 +1,41 @@
+use crate::matching::market::MarketSummary;
+use crate::utils::protocol::{
+    MATCHER_ADDRESS, MatcherRequest, MatcherResponse, ProtocolError, read_message, write_message,
+};
+
+use clap::Parser;
+use tokio::net::TcpStream;
+
+#[derive(Parser, Debug)]
+#[command(about = "Queries the aggregated depth, midpoint and last trade of a pair")]
+pub struct Market {
+    /// Faucet id of one asset of the pair
+    #[arg(long)]
+    faucet_a: String,
+
+    /// Faucet id of the other asset of the pair
+    #[arg(long)]
+    faucet_b: String,
+
+    /// Rounds the depth prices to multiples of this tick, in quote units per base unit, the
+    /// midpoint is taken between the rounded levels. The matcher depth tick applies when coarser.
+    #[arg(long)]
+    tick: Option<f64>,
+}
+
+impl Market {
+    pub async fn run(&self) -> Result<MarketSummary, ProtocolError> {
+        let mut stream = TcpStream::connect(MATCHER_ADDRESS).await?;
+        let request = MatcherRequest::QueryMarket {
+            faucet_a: self.faucet_a.clone(),
+            faucet_b: self.faucet_b.clone(),
+            tick: self.tick,
+        };
+        write_message(&mut stream, &request).await?;
+
+        match read_message(&mut stream).await? {
+            MatcherResponse::Market(summary) => Ok(summary),
+            _ => Err(ProtocolError::UnexpectedResponse),
+        }
+    }
+}
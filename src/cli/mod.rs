@@ -1,3 +1,8 @@
+pub mod account;
 pub mod cancel_order;
 pub mod consume_swapped;
+pub mod fetch_notes;
+pub mod market;
 pub mod open_order;
+pub mod recover_payout;
+pub mod subscribe;
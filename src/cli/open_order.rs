@@ -1,15 +1,23 @@
+use crate::utils::attestation::{AttestationError, Verifier};
+use crate::utils::common::OrderFee;
+use crate::utils::common::OrderTerms;
+use crate::utils::common::SwapNoteError;
+use crate::utils::common::TimeInForce;
 use crate::utils::common::client_setup;
-use crate::utils::common::create_partial_swap_note;
 use crate::utils::common::get_account;
+use crate::utils::swap_note::SwapNoteBuilder;
 
 use clap::Parser;
 use miden_client::ClientError;
 use miden_client::account::AccountId;
 use miden_client::note::Note;
-use miden_client::transaction::TransactionRequestBuilder;
+use miden_client::transaction::{TransactionRequestBuilder, TransactionRequestError};
 use miden_objects::AccountIdError;
 use miden_objects::Felt;
-use miden_objects::asset::FungibleAsset;
+use miden_objects::account::AccountType;
+use miden_objects::asset::{AssetVault, FungibleAsset};
+use miden_objects::block::BlockNumber;
+use miden_objects::note::{NoteExecutionHint, NoteType};
 use miden_objects::transaction::OutputNote;
 use rand::Rng;
 use sha2::Digest;
@@ -37,72 +45,303 @@ pub struct OpenOrder {
     /// Price
     #[arg(long)]
     price: u64,
+
+    /// Number of blocks after the current one during which the order can be filled
+    #[arg(long, conflicts_with = "expires_at")]
+    expires_in_blocks: Option<u32>,
+
+    /// Last block at which the order can be filled
+    #[arg(long)]
+    expires_at: Option<u32>,
+
+    /// Smallest amount of the requested asset accepted by a partial fill
+    #[arg(long, default_value_t = 0)]
+    min_fill: u64,
+
+    /// gtc rests until filled, aon only accepts a complete fill, fok is dropped unless filled on arrival
+    #[arg(long, value_enum, default_value_t = TimeInForce::GoodTillCancelled)]
+    time_in_force: TimeInForce,
+
+    /// Account of the matcher, the only account besides the creator allowed to fill the order
+    #[arg(long)]
+    executor_id: Option<String>,
+
+    /// Fee in basis points of every fill, paid in the requested asset
+    #[arg(long, default_value_t = 0, requires = "fee_recipient")]
+    fee_bps: u16,
+
+    /// Account receiving the fee
+    #[arg(long)]
+    fee_recipient: Option<String>,
+
+    /// Account receiving the proceeds of the order, defaults to the user account
+    #[arg(long)]
+    payout_id: Option<String>,
+
+    /// Id carried in the aux field of the order notes to correlate fills with the order
+    #[arg(long, default_value_t = 0)]
+    client_order_id: u64,
+
+    /// First block at which the order should be matched
+    #[arg(long, conflicts_with = "block_slot")]
+    after_block: Option<u32>,
+
+    /// Only match the order in the given slot of every round, lengths are powers of 2
+    #[arg(long, num_args = 3, value_names = ["ROUND_LEN", "SLOT_LEN", "SLOT_OFFSET"])]
+    block_slot: Option<Vec<u8>>,
+
+    /// Only match the order at the reference price of the matcher, the price stays the limit
+    #[arg(long)]
+    pegged: bool,
+
+    /// Public orders and their fills are published on chain, private orders only sent to the matcher
+    #[arg(long, value_enum, default_value_t = Visibility::Private)]
+    visibility: Visibility,
+
+    /// Measurement the attestation report of the matcher must match, repeatable. The order is
+    /// then sealed to the attested transport key of the matcher
+    #[arg(long = "expected-measurement", value_name = "HEX")]
+    expected_measurements: Vec<String>,
+
+    /// Accepts attestation reports of the software mock provider, for testing outside of a TEE
+    #[arg(long, requires = "expected_measurements")]
+    allow_mock_attestation: bool,
+}
+
+/// Whether an order is posted to a lit book or kept dark
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Visibility {
+    /// Uses the SWAPp script, the order and its P2ID and SWAPp' notes are public
+    Public,
+    /// Uses the PRIVATE_SWAPp script, the order and its P2ID and SWAPp' notes are private
+    #[default]
+    Private,
+}
+
+impl From<Visibility> for NoteType {
+    fn from(visibility: Visibility) -> Self {
+        match visibility {
+            Visibility::Public => NoteType::Public,
+            Visibility::Private => NoteType::Private,
+        }
+    }
+}
+
+/// Reasons an order is rejected before any transaction is built
+#[derive(Error, Debug, PartialEq)]
+pub enum PreflightError {
+    #[error("{0} is not a fungible faucet")]
+    NotFungibleFaucet(AccountId),
+
+    #[error("offered and requested assets are both issued by {0}")]
+    SameAsset(AccountId),
+
+    #[error("amount {0} is zero or exceeds the fungible asset maximum of {max}", max = FungibleAsset::MAX_AMOUNT)]
+    InvalidAmount(u64),
+
+    #[error("minimum fill {min_fill} exceeds the requested amount {requested}")]
+    InvalidMinFill { min_fill: u64, requested: u64 },
+
+    #[error("insufficient balance of {faucet_id}: {available} available, {required} required")]
+    InsufficientBalance {
+        faucet_id: AccountId,
+        available: u64,
+        required: u64,
+    },
 }
 
 #[derive(Error, Debug)]
 pub enum OrderError {
-    #[error("Client error:")]
+    #[error("client error: {0}")]
     Client(#[from] ClientError),
 
-    #[error("Account error:")]
+    #[error("invalid account id: {0}")]
     InvalidAccountID(#[from] AccountIdError),
 
-    #[error("hex error:")]
+    #[error("invalid hex value: {0}")]
     InvalidFelt(#[from] miden_objects::utils::HexParseError),
 
+    #[error("order rejected: {0}")]
+    Preflight(#[from] PreflightError),
+
+    #[error("invalid transaction request: {0}")]
+    TransactionRequest(#[from] TransactionRequestError),
+
+    #[error("transaction execution failed: {0}")]
+    TransactionExecution(#[source] ClientError),
+
+    #[error("transaction submission failed: {0}")]
+    TransactionSubmission(#[source] ClientError),
+
     #[error("order already consumed")]
     OrderAlreadyConsumed,
+
+    #[error("swap note error: {0}")]
+    SwapNote(#[from] SwapNoteError),
+
+    #[error("client order id {0} is not a valid field element")]
+    InvalidClientOrderId(u64),
 }
 
 impl OpenOrder {
-    pub(crate) async fn run(&self) -> Result<Note, OrderError> {
+    /// Verifier of the attestation report of the matcher, `None` without expected measurements
+    pub fn verifier(&self) -> Result<Option<Verifier>, AttestationError> {
+        if self.expected_measurements.is_empty() {
+            return Ok(None);
+        }
+        let verifier = Verifier::from_hex(&self.expected_measurements)?
+            .allow_mock(self.allow_mock_attestation);
+        Ok(Some(verifier))
+    }
+
+    pub async fn run(&self) -> Result<Note, OrderError> {
         let mut client = client_setup().await?;
 
         let user_id = AccountId::from_hex(&self.user_id)?;
-        let user = get_account(&mut client, user_id).await.unwrap();
+        let user = get_account(&mut client, user_id).await?;
 
         let offered_asset_id = AccountId::from_hex(&self.offered_asset)?;
-        let offered_asset = get_account(&mut client, offered_asset_id).await?;
-
         let requested_asset_id = AccountId::from_hex(&self.requested_asset)?;
-        let requested_asset = get_account(&mut client, requested_asset_id).await?;
-
-        // offered asset amount
-        let asset_a = FungibleAsset::new(offered_asset.id(), self.offered_amount).unwrap();
 
         //TODO: requested asset amount should be based on the price
-        // requested asset amount
-        let asset_b = FungibleAsset::new(requested_asset.id(), self.offered_amount).unwrap();
+        let (asset_a, asset_b) = preflight_check(
+            user.vault(),
+            offered_asset_id,
+            self.offered_amount,
+            requested_asset_id,
+            self.offered_amount,
+        )?;
+        if self.min_fill > asset_b.amount() {
+            return Err(PreflightError::InvalidMinFill {
+                min_fill: self.min_fill,
+                requested: asset_b.amount(),
+            }
+            .into());
+        }
+
+        let executor = self
+            .executor_id
+            .as_deref()
+            .map(AccountId::from_hex)
+            .transpose()?;
+
+        let payout = self
+            .payout_id
+            .as_deref()
+            .map(AccountId::from_hex)
+            .transpose()?;
+
+        let aux = Felt::try_from(self.client_order_id)
+            .map_err(|_| OrderError::InvalidClientOrderId(self.client_order_id))?;
+
+        let execution_hint = match (self.after_block, self.block_slot.as_deref()) {
+            (Some(block_num), _) => {
+                NoteExecutionHint::after_block(block_num.into()).map_err(SwapNoteError::from)?
+            }
+            (None, Some(&[round_len, slot_len, slot_offset])) => {
+                NoteExecutionHint::on_block_slot(round_len, slot_len, slot_offset)
+            }
+            _ => NoteExecutionHint::always(),
+        };
+
+        let fee = match &self.fee_recipient {
+            Some(recipient) if self.fee_bps > 0 => Some(OrderFee {
+                bps: self.fee_bps,
+                recipient: AccountId::from_hex(recipient)?,
+            }),
+            _ => None,
+        };
+
+        let expires_at = match (self.expires_in_blocks, self.expires_at) {
+            (Some(blocks), _) => {
+                let block_num = client.get_sync_height().await?;
+                Some(block_num + blocks)
+            }
+            (None, Some(expires_at)) => Some(BlockNumber::from(expires_at)),
+            (None, None) => None,
+        };
 
         // Set up the swap transaction
         let serial_num = get_serial_num(user_id);
-        let fill_number = 0;
 
         // Create the partial swap note
-        let swap_note = create_partial_swap_note(
-            user.id(),
-            user.id(),
-            asset_a.into(),
-            asset_b.into(),
-            serial_num,
-            fill_number,
-        )
-        .unwrap();
+        let swap_note = SwapNoteBuilder::new(user.id(), asset_a.into(), asset_b.into(), serial_num)
+            .note_type(self.visibility.into())
+            .terms(OrderTerms {
+                expires_at,
+                min_fill: self.min_fill,
+                time_in_force: self.time_in_force,
+                executor,
+                fee,
+                payout,
+                aux,
+                execution_hint,
+                pegged: self.pegged,
+            })
+            .build()?;
 
         let note_req = TransactionRequestBuilder::new()
             .with_own_output_notes(vec![OutputNote::Full(swap_note.clone())])
-            .build()
-            .unwrap();
+            .build()?;
 
-        let tx_result = client.new_transaction(user.id(), note_req).await.unwrap();
+        let tx_result = client
+            .new_transaction(user.id(), note_req)
+            .await
+            .map_err(OrderError::TransactionExecution)?;
 
-        let _ = client.submit_transaction(tx_result).await;
+        client
+            .submit_transaction(tx_result)
+            .await
+            .map_err(OrderError::TransactionSubmission)?;
         client.sync_state().await?;
 
         Ok(swap_note)
     }
 }
 
+/// Checks an order against the user vault before any transaction is built
+///
+/// Both faucets must be distinct fungible faucets, both amounts must be valid fungible amounts
+/// and the vault must hold at least `offered_amount` of the offered asset.
+/// Returns the offered and requested assets.
+pub fn preflight_check(
+    vault: &AssetVault,
+    offered_faucet_id: AccountId,
+    offered_amount: u64,
+    requested_faucet_id: AccountId,
+    requested_amount: u64,
+) -> Result<(FungibleAsset, FungibleAsset), PreflightError> {
+    for faucet_id in [offered_faucet_id, requested_faucet_id] {
+        if faucet_id.account_type() != AccountType::FungibleFaucet {
+            return Err(PreflightError::NotFungibleFaucet(faucet_id));
+        }
+    }
+    if offered_faucet_id == requested_faucet_id {
+        return Err(PreflightError::SameAsset(offered_faucet_id));
+    }
+
+    let offered_asset = FungibleAsset::new(offered_faucet_id, offered_amount)
+        .ok()
+        .filter(|asset| asset.amount() > 0)
+        .ok_or(PreflightError::InvalidAmount(offered_amount))?;
+    let requested_asset = FungibleAsset::new(requested_faucet_id, requested_amount)
+        .ok()
+        .filter(|asset| asset.amount() > 0)
+        .ok_or(PreflightError::InvalidAmount(requested_amount))?;
+
+    // the faucet id was checked to be fungible so the balance lookup cannot fail
+    let available = vault.get_balance(offered_faucet_id).unwrap_or_default();
+    if available < offered_amount {
+        return Err(PreflightError::InsufficientBalance {
+            faucet_id: offered_faucet_id,
+            available,
+            required: offered_amount,
+        });
+    }
+
+    Ok((offered_asset, requested_asset))
+}
+
 /// Generates a random serial number
 /// hash(AccountId||random u64)
 /// AccountId is treated as domain separation tag
@@ -110,7 +349,7 @@ pub fn get_serial_num(acc_id: AccountId) -> [Felt; 4] {
     let mut rng = rand::rng();
     let num = rng.r#random::<u64>();
     let data = format!("{}{}", acc_id.to_hex(), num);
-    let hash: [u8; 32] = sha2::Sha256::digest(&data.as_bytes()).into();
+    let hash: [u8; 32] = sha2::Sha256::digest(data.as_bytes()).into();
 
     let serial_num: [Felt; 4] = [
         Felt::new(u64::from_be_bytes(hash[0..8].try_into().unwrap())),
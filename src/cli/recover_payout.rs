@@ -0,0 +1,136 @@
+use crate::utils::common::{SwapNoteError, client_setup, get_account, reconstruct_p2id_note};
+use crate::utils::swap_note::SwapOrder;
+
+use clap::Parser;
+use miden_client::ClientError;
+use miden_client::account::AccountId;
+use miden_client::note::{Note, NoteFile, NoteId};
+use miden_objects::AccountIdError;
+use miden_objects::utils::{HexParseError, Serializable};
+use std::path::PathBuf;
+use thiserror::Error;
+
+#[derive(Parser, Debug)]
+#[command(about = "Rebuilds the private P2ID payout of a filled order and imports it")]
+pub struct RecoverPayout {
+    /// Unique user identifier
+    #[arg(long)]
+    user_id: String,
+
+    /// Id of the SWAPp note created by open-order
+    #[arg(long)]
+    order_id: String,
+
+    /// Swap count of the consumed note (0 for the original order)
+    #[arg(long, default_value_t = 0)]
+    fill_number: u64,
+
+    /// Amount of the requested asset filled by the consumed note, the filled amount of its note
+    /// args. Required as neither a partial fill nor a later fill can be told from the order alone
+    #[arg(long)]
+    filled_amount: u64,
+
+    /// Price improvement paid on top of the filled amount by a pegged or auction fill, the
+    /// improvement of the note args of the consumed note
+    #[arg(long, default_value_t = 0)]
+    price_improvement: u64,
+
+    /// File the P2ID note is written to when the order pays out to another account, to be
+    /// imported by the client of that account
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Error, Debug)]
+pub enum RecoverPayoutError {
+    #[error("client error: {0}")]
+    Client(#[from] ClientError),
+
+    #[error("invalid account id: {0}")]
+    InvalidAccountID(#[from] AccountIdError),
+
+    #[error("invalid note id: {0}")]
+    InvalidNoteId(#[from] HexParseError),
+
+    #[error("swap note error: {0}")]
+    SwapNote(#[from] SwapNoteError),
+
+    #[error("order not found in the local store")]
+    OrderNotFound,
+
+    #[error("account is not the creator of the order")]
+    NotOrderCreator,
+
+    #[error("the order pays out to {0}, pass --output to write the P2ID note to a file")]
+    OtherPayoutAccount(AccountId),
+
+    #[error("filled amount and price improvement overflow")]
+    InvalidPaidAmount,
+
+    #[error("failed to write the note file: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl RecoverPayout {
+    pub async fn run(&self) -> Result<NoteId, RecoverPayoutError> {
+        let mut client = client_setup().await?;
+
+        let user_id = AccountId::from_hex(&self.user_id)?;
+        let user = get_account(&mut client, user_id).await?;
+
+        let order_id = NoteId::try_from_hex(self.order_id.as_str())?;
+        let record = client
+            .get_output_note(order_id)
+            .await?
+            .ok_or(RecoverPayoutError::OrderNotFound)?;
+
+        // the payout can only be committed after the order itself
+        let after_block_num = record
+            .inclusion_proof()
+            .map(|proof| proof.location().block_num())
+            .unwrap_or_default();
+        let order = Note::try_from(record).map_err(|_| RecoverPayoutError::OrderNotFound)?;
+        let swap_order = SwapOrder::try_from(&order)?;
+
+        if swap_order.creator() != user.id() {
+            return Err(RecoverPayoutError::NotOrderCreator);
+        }
+
+        // the P2ID carries the paid amount, the improvement included
+        let paid = self
+            .filled_amount
+            .checked_add(self.price_improvement)
+            .ok_or(RecoverPayoutError::InvalidPaidAmount)?;
+        let (details, tag) = reconstruct_p2id_note(&order, self.fill_number, paid)?;
+        let note_id = details.id();
+        let note_file = NoteFile::NoteDetails {
+            details,
+            after_block_num,
+            tag: Some(tag),
+        };
+
+        // a note paid out to another account cannot be consumed from this store
+        let payout = swap_order.inputs.payout_account();
+        if payout != user.id() {
+            let path = self
+                .output
+                .as_ref()
+                .ok_or(RecoverPayoutError::OtherPayoutAccount(payout))?;
+            std::fs::write(path, note_file.to_bytes())?;
+
+            println!(
+                "P2ID note {} of {} written to {}",
+                note_id.to_hex(),
+                payout.to_hex(),
+                path.display()
+            );
+            return Ok(note_id);
+        }
+
+        client.import_note(note_file).await?;
+        client.sync_state().await?;
+
+        println!("P2ID note imported: {}", note_id.to_hex());
+        Ok(note_id)
+    }
+}
@@ -0,0 +1,32 @@
+use crate::utils::protocol::{
+    MATCHER_ADDRESS, MatcherRequest, MatcherResponse, ProtocolError, read_message, write_message,
+};
+
+use clap::Parser;
+use tokio::net::TcpStream;
+
+#[derive(Parser, Debug)]
+#[command(about = "Prints the anonymized market events of the matcher as they happen")]
+pub struct Subscribe {
+    /// Stop after this many events, runs until the matcher closes the connection when not set
+    #[arg(long)]
+    limit: Option<usize>,
+}
+
+impl Subscribe {
+    pub async fn run(&self) -> Result<usize, ProtocolError> {
+        let mut stream = TcpStream::connect(MATCHER_ADDRESS).await?;
+        write_message(&mut stream, &MatcherRequest::Subscribe).await?;
+
+        let mut received = 0;
+        while self.limit.is_none_or(|limit| received < limit) {
+            let MatcherResponse::Event(event) = read_message(&mut stream).await? else {
+                return Err(ProtocolError::UnexpectedResponse);
+            };
+            println!("{:?}", event);
+            received += 1;
+        }
+
+        Ok(received)
+    }
+}
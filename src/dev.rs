@@ -0,0 +1,144 @@
+use clap::{Parser, Subcommand};
+use miden_client::account::{AccountId, AccountStorageMode};
+use miden_client::keystore::FilesystemKeyStore;
+use std::path::{Path, PathBuf};
+
+use miden_dark_pool::utils::common::{KEYSTORE_PATH, client_setup, create_wallet};
+use miden_dark_pool::utils::dev::{
+    DEV_ACCOUNTS_PATH, DevAccounts, Scenario, TokenConfig, create_faucet, mint_and_consume,
+    seed_scenario,
+};
+
+#[derive(Parser, Debug)]
+#[command(name = "dev", about = "Local development tooling for the dark pool")]
+struct DevArgs {
+    /// Name to account id mapping of the dev accounts
+    #[arg(long, default_value = DEV_ACCOUNTS_PATH)]
+    accounts: PathBuf,
+
+    #[command(subcommand)]
+    command: DevCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum DevCommand {
+    /// Deploys a fungible faucet, named after its symbol unless a name is given
+    Faucet {
+        #[arg(long)]
+        symbol: String,
+
+        #[arg(long, default_value_t = 8)]
+        decimals: u8,
+
+        #[arg(long, default_value_t = 1_000_000_000)]
+        max_supply: u64,
+
+        #[arg(long)]
+        name: Option<String>,
+    },
+
+    /// Creates a named public wallet
+    Wallet {
+        #[arg(long)]
+        name: String,
+    },
+
+    /// Mints tokens of a faucet to accounts, by name or hex id
+    Mint {
+        #[arg(long)]
+        faucet: String,
+
+        #[arg(long)]
+        amount: u64,
+
+        #[arg(long, required = true)]
+        to: Vec<String>,
+    },
+
+    /// Deploys the tokens and funds the traders of a yaml scenario
+    Seed {
+        #[arg(long)]
+        scenario: PathBuf,
+    },
+
+    /// Lists the named dev accounts
+    List,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = DevArgs::parse();
+    let mut accounts = DevAccounts::load(&args.accounts)?;
+
+    if let DevCommand::List = args.command {
+        for (name, account_id) in accounts.iter() {
+            println!("{}: {}", name, account_id);
+        }
+        return Ok(());
+    }
+
+    let mut client = client_setup().await?;
+    let keystore = FilesystemKeyStore::new(KEYSTORE_PATH.into())?;
+
+    match args.command {
+        DevCommand::Faucet {
+            symbol,
+            decimals,
+            max_supply,
+            name,
+        } => {
+            let token = TokenConfig {
+                symbol,
+                decimals,
+                max_supply,
+            };
+            let faucet = create_faucet(&mut client, &keystore, &token).await?;
+            let name = name.unwrap_or(token.symbol);
+            save_account(&mut accounts, &args.accounts, &name, faucet.id())?;
+        }
+
+        DevCommand::Wallet { name } => {
+            let wallet = create_wallet(&mut client, &keystore, AccountStorageMode::Public).await?;
+            client.sync_state().await?;
+            save_account(&mut accounts, &args.accounts, &name, wallet.id())?;
+        }
+
+        DevCommand::Mint { faucet, amount, to } => {
+            let faucet_id = accounts.resolve(&faucet)?;
+            for target in &to {
+                let target_id = accounts.resolve(target)?;
+                mint_and_consume(&mut client, faucet_id, target_id, amount).await?;
+                println!("Minted {} {} to {}", amount, faucet, target);
+            }
+        }
+
+        DevCommand::Seed { scenario } => {
+            let scenario = Scenario::load(&scenario)?;
+            let result = seed_scenario(&mut client, &keystore, &scenario, &mut accounts).await;
+
+            // Keep the names of the accounts created before a failure
+            accounts.save(&args.accounts)?;
+            result?;
+
+            for (name, account_id) in accounts.iter() {
+                println!("{}: {}", name, account_id);
+            }
+        }
+
+        DevCommand::List => unreachable!("handled before the client setup"),
+    }
+
+    Ok(())
+}
+
+fn save_account(
+    accounts: &mut DevAccounts,
+    path: &Path,
+    name: &str,
+    account_id: AccountId,
+) -> anyhow::Result<()> {
+    accounts.insert(name, account_id);
+    accounts.save(path)?;
+    println!("{}: {}", name, account_id.to_hex());
+    Ok(())
+}
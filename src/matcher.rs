@@ -1,68 +1,739 @@
-use bincode;
-use miden_client::note::Note;
+use clap::Parser;
+use miden_client::Client;
+use miden_client::account::AccountId;
+use miden_client::note::{Note, NoteId};
+use miden_client::rpc::domain::note::NetworkNote;
+use miden_client::rpc::{NodeRpcClient, TonicRpcClient};
+use miden_dark_pool::matching::book::SelfTradePrevention;
+use miden_dark_pool::matching::engine::{
+    CancelReason, FeePolicy, Input, MatcherConfig, MatcherCore, MatchingMode, Output,
+    SettlementPlan,
+};
+use miden_dark_pool::matching::events::{EventBus, MarketEvent};
+use miden_dark_pool::matching::mailbox::{KeyChallenge, Mailbox};
+use miden_dark_pool::matching::market::MarketSummary;
+use miden_dark_pool::matching::oracle::{StaticPrices, parse_price_line};
+use miden_dark_pool::matching::settlement::settle_plan;
+use miden_dark_pool::utils::attestation::{AttestationProvider, Attestor, MockProvider};
+use miden_dark_pool::utils::common::{client_setup_with_store, rpc_setup};
+use miden_dark_pool::utils::protocol::{
+    MATCHER_ADDRESS, MatcherRequest, MatcherResponse, read_message, write_message,
+};
+use miden_dark_pool::utils::swap_note::TradingPair;
+use miden_dark_pool::utils::transport::{SealedNote, TransportKey};
 use miden_lib::utils::Deserializable;
-use miden_tx::utils::ToHex;
-use sha2::{Digest, Sha256};
-use tokio::io::AsyncReadExt;
-use tokio::net::TcpListener;
-use winter_utils::Serializable;
+use miden_objects::block::BlockNumber;
+use miden_objects::utils::bytes_to_hex_string;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, mpsc, oneshot};
 
-mod utils;
-use utils::common::MidenNote;
+// how often resting orders are checked for expiry and scheduled orders for activation
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Parser, Debug)]
+#[command(name = "matcher", about = "Dark pool matcher")]
+struct MatcherArgs {
+    /// Account settling matched orders, orders are only collected when not set
+    #[arg(long)]
+    executor_id: Option<String>,
+
+    /// Sqlite store of the matcher client
+    #[arg(long, default_value = "./matcher_store.sqlite3")]
+    store: String,
+
+    /// Keystore holding the executor keys
+    #[arg(long, default_value = "./matcher_keystore")]
+    keystore: String,
+
+    /// Smallest fee in basis points an order must pay to be accepted
+    #[arg(long, default_value_t = 0)]
+    min_fee_bps: u16,
+
+    /// Account the fees must be paid to, defaults to the executor
+    #[arg(long)]
+    fee_recipient: Option<String>,
+
+    /// Reference prices of pegged orders, `<FAUCET_A> <FAUCET_B> <PRICE>` lines, `-` reads
+    /// updates from stdin. A file is reloaded on every expiry check.
+    #[arg(long)]
+    price_feed: Option<String>,
+
+    /// continuous matches every order on arrival, batch clears every pair at a uniform price
+    /// once per batch interval
+    #[arg(long, value_enum, default_value_t = MatchingMode::Continuous)]
+    mode: MatchingMode,
+
+    /// Length of the order collection window of batch auctions, in seconds
+    #[arg(long, default_value_t = 10)]
+    batch_interval_secs: u64,
+
+    /// What happens to an order crossing a resting order of its own creator, orders of the
+    /// same creator are never matched together
+    #[arg(long, value_enum, default_value_t = SelfTradePrevention::Skip)]
+    self_trade_prevention: SelfTradePrevention,
+
+    /// Rounds the prices of the published depth to multiples of this tick, in quote units per
+    /// base unit, the midpoint is taken between the rounded levels. Market queries cannot ask
+    /// for a finer tick.
+    #[arg(long)]
+    depth_tick: Option<f64>,
+}
+
+// Requests forwarded by the connection handlers to the matching loop
+enum Command {
+    SubmitOrder {
+        note: Box<Note>,
+        transport_key: [u8; 32],
+    },
+    FetchChallenge {
+        account_id: AccountId,
+        reply: oneshot::Sender<Option<(KeyChallenge, SealedNote)>>,
+    },
+    FetchNotes {
+        challenge: KeyChallenge,
+        secret: Vec<u8>,
+        reply: oneshot::Sender<Option<Vec<SealedNote>>>,
+    },
+    AckNotes {
+        challenge: KeyChallenge,
+        secret: Vec<u8>,
+        ephemeral_keys: Vec<[u8; 32]>,
+    },
+    QueryMarket {
+        pair: TradingPair,
+        tick: Option<f64>,
+        reply: oneshot::Sender<MarketSummary>,
+    },
+    Subscribe {
+        reply: oneshot::Sender<Subscription>,
+    },
+    ReferencePrice {
+        pair: TradingPair,
+        price: f64,
+    },
+}
+
+// Receiver of a new subscriber and the depth of the book when it subscribed
+type Subscription = (broadcast::Receiver<MarketEvent>, Vec<MarketEvent>);
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let listener = TcpListener::bind("127.0.0.1:8080").await?;
-    println!("Matcher listening on 127.0.0.1:8080");
+    let args = MatcherArgs::parse();
+
+    let executor = match &args.executor_id {
+        Some(executor_id) => {
+            let executor_id = AccountId::from_hex(executor_id)?;
+            let client = client_setup_with_store(&args.store, &args.keystore).await?;
+            println!("Settling matches with executor {}", executor_id.to_hex());
+            Some((client, executor_id))
+        }
+        None => None,
+    };
+
+    let fee_recipient = match &args.fee_recipient {
+        Some(fee_recipient) => Some(AccountId::from_hex(fee_recipient)?),
+        None => executor.as_ref().map(|(_, executor_id)| *executor_id),
+    };
+    let mut core = MatcherCore::new(MatcherConfig {
+        executor: executor.as_ref().map(|(_, executor_id)| *executor_id),
+        mode: args.mode,
+        fee_policy: FeePolicy {
+            min_bps: args.min_fee_bps,
+            recipient: fee_recipient,
+        },
+        self_trade_prevention: args.self_trade_prevention,
+        depth_tick: args.depth_tick,
+    });
+
+    // orders are sealed to a transport key generated at every start, attested by the provider
+    let provider = MockProvider::measure(std::env::current_exe()?)?;
+    println!(
+        "Attestation measurement (mock provider): {}",
+        bytes_to_hex_string(provider.measurement())
+    );
+    let attestor = Arc::new(Attestor::new(TransportKey::generate(), provider));
+
+    let listener = TcpListener::bind(MATCHER_ADDRESS).await?;
+    println!("Matcher listening on {}", MATCHER_ADDRESS);
+
+    let (sender, mut receiver) = mpsc::channel(64);
+
+    let price_file = args.price_feed.clone().filter(|feed| feed != "-");
+    let mut host = Host {
+        executor,
+        rpc: rpc_setup(),
+        mailbox: Mailbox::new(),
+        events: EventBus::new(),
+    };
+    if let Some(path) = &price_file {
+        host.drive(&mut core, Input::ReferencePrices(load_prices(path)?))
+            .await;
+    }
+    if args.price_feed.as_deref() == Some("-") {
+        tokio::spawn(read_stdin_prices(sender.clone()));
+    }
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((socket, _)) => {
+                    tokio::spawn(handle_connection(socket, sender.clone(), attestor.clone()));
+                }
+                Err(e) => eprintln!("Failed to accept connection: {}", e),
+            }
+        }
+    });
+
+    // the core and the host are owned by this loop only
+    let mut expiry_check = tokio::time::interval(EXPIRY_CHECK_INTERVAL);
+    let mut batch = tokio::time::interval(Duration::from_secs(args.batch_interval_secs.max(1)));
+
+    loop {
+        let command = tokio::select! {
+            command = receiver.recv() => match command {
+                Some(command) => command,
+                None => break,
+            },
+            _ = expiry_check.tick() => {
+                if let Some(path) = &price_file {
+                    match load_prices(path) {
+                        Ok(prices) => {
+                            host.drive(&mut core, Input::ReferencePrices(prices)).await;
+                        }
+                        Err(e) => eprintln!("Failed to reload prices: {}", e),
+                    }
+                }
+                let block_num = host.sync().await;
+                verify_transport_keys(&host.rpc, &mut host.mailbox).await;
+                host.drive(&mut core, Input::ExpiryCheck { block_num }).await;
+                continue;
+            }
+            _ = batch.tick(), if args.mode == MatchingMode::Batch => {
+                let block_num = host.sync().await;
+                host.drive(&mut core, Input::BatchAuction { block_num }).await;
+                continue;
+            }
+        };
+
+        match command {
+            Command::SubmitOrder {
+                note,
+                transport_key,
+            } => {
+                let block_num = host.sync().await;
+                let input = Input::SubmitOrder {
+                    note,
+                    transport_key,
+                    block_num,
+                };
+                host.drive(&mut core, input).await;
+                println!("Orders in book: {}", core.book().len());
+            }
+
+            Command::FetchChallenge { account_id, reply } => {
+                let _ = reply.send(host.mailbox.challenge(account_id));
+            }
+
+            Command::FetchNotes {
+                challenge,
+                secret,
+                reply,
+            } => {
+                let _ = reply.send(host.mailbox.peek(&challenge, &secret));
+            }
+
+            Command::AckNotes {
+                challenge,
+                secret,
+                ephemeral_keys,
+            } => {
+                if !host
+                    .mailbox
+                    .acknowledge(&challenge, &secret, &ephemeral_keys)
+                {
+                    eprintln!("Rejected the acknowledgement of unchallenged notes");
+                }
+            }
+
+            Command::QueryMarket { pair, tick, reply } => {
+                let input = Input::QueryMarket { pair, tick };
+                if let Some(Output::Market(summary)) = host.drive(&mut core, input).await.pop() {
+                    let _ = reply.send(summary);
+                }
+            }
+
+            Command::Subscribe { reply } => {
+                if let Some(Output::Depth(snapshots)) =
+                    host.drive(&mut core, Input::QueryDepth).await.pop()
+                {
+                    let snapshots = snapshots.into_iter().map(MarketEvent::Depth).collect();
+                    let _ = reply.send((host.events.subscribe(), snapshots));
+                }
+            }
+
+            Command::ReferencePrice { pair, price } => {
+                host.drive(&mut core, Input::ReferencePrice { pair, price })
+                    .await;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Carries out the outputs of the matcher core: settlements with the executor client, note
+/// delivery through the mailbox and market events for the subscribers
+struct Host {
+    executor: Option<(Client, AccountId)>,
+    rpc: Arc<TonicRpcClient>,
+    mailbox: Mailbox,
+    events: EventBus,
+}
+
+impl Host {
+    /// Syncs the executor client, returns the synced block number or `None` if there is no
+    /// executor or the sync failed
+    async fn sync(&mut self) -> Option<BlockNumber> {
+        let (client, _) = self.executor.as_mut()?;
+        match client.sync_state().await {
+            Ok(summary) => Some(summary.block_num),
+            Err(e) => {
+                eprintln!("Failed to sync: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Hands `input` to the core and carries out its outputs, answering every settlement
+    /// request until the core is done
+    ///
+    /// Returns the replies to queries, [Output::Market] and [Output::Depth].
+    async fn drive(&mut self, core: &mut MatcherCore, input: Input) -> Vec<Output> {
+        let mut replies = Vec::new();
+        let mut input = input;
+
+        loop {
+            let outputs = match core.handle(input) {
+                Ok(outputs) => outputs,
+                Err(e) => {
+                    eprintln!("Matcher core error: {}", e);
+                    return replies;
+                }
+            };
+
+            let mut plan = None;
+            for output in outputs {
+                match output {
+                    Output::Accepted {
+                        id,
+                        creator,
+                        transport_key,
+                    } => {
+                        println!("Accepted order {}", id.to_hex());
+                        self.mailbox.propose(id, creator, transport_key);
+                    }
+                    Output::Rejected { id, reason } => {
+                        eprintln!("Rejected order {}: {}", id.to_hex(), reason);
+                    }
+                    Output::Cancelled { id, reason } => {
+                        self.mailbox.withdraw(id);
+                        match reason {
+                            CancelReason::Expired => {
+                                println!("Dropped expired order {}", id.to_hex())
+                            }
+                            CancelReason::FillOrKill => {
+                                println!("Dropped unfilled fill-or-kill order {}", id.to_hex())
+                            }
+                            CancelReason::SelfTrade => {
+                                println!("Cancelled self-trading order {}", id.to_hex())
+                            }
+                        }
+                    }
+                    Output::Event(event) => self.events.publish(event),
+                    Output::AuctionCleared { pair, clearing } => println!(
+                        "Auction of {}/{} clears at {} for {}",
+                        pair.base.to_hex(),
+                        pair.quote.to_hex(),
+                        clearing.price,
+                        clearing.volume
+                    ),
+                    Output::Settle(settle) => plan = Some(settle),
+                    reply @ (Output::Market(_) | Output::Depth(_)) => replies.push(reply),
+                }
+            }
+
+            let Some(plan) = plan else {
+                return replies;
+            };
+            input = self.settle(&plan).await;
+        }
+    }
+
+    /// Settles `plan` with the executor account and delivers the resulting notes
+    async fn settle(&mut self, plan: &SettlementPlan) -> Input {
+        let Some((client, executor_id)) = self.executor.as_mut() else {
+            return Input::SettlementFailed;
+        };
+        let taker = plan.fills().last().map(|fill| fill.order.id());
+
+        let settlement = match settle_plan(client, *executor_id, plan).await {
+            Ok(settlement) => settlement,
+            Err(e) => {
+                if let Some(taker) = taker {
+                    eprintln!("Failed to settle {}: {}", taker.to_hex(), e);
+                }
+                return Input::SettlementFailed;
+            }
+        };
+        println!("Settled {} orders in one transaction", plan.fills().len());
+
+        // the settled orders are on chain, their keys can be confirmed before the delivery
+        verify_transport_keys(&self.rpc, &mut self.mailbox).await;
+
+        for (account_id, note_file) in settlement.deliveries {
+            // fees paid to the executor are kept by the matcher client
+            if account_id == *executor_id {
+                if let Err(e) = client.import_note(note_file).await {
+                    eprintln!("Failed to import fee note: {}", e);
+                }
+            } else if !self.mailbox.deliver(account_id, &note_file) {
+                eprintln!("No transport key for {}", account_id.to_hex());
+            }
+        }
+
+        Input::Settled {
+            successors: settlement.successors,
+        }
+    }
+}
+
+/// Confirms the transport keys proposed with orders that are on chain, see [Mailbox::confirm]
+///
+/// The sender of a note is only authenticated once the note is on chain. The key is kept
+/// for private orders sent by their creator, anyone can resubmit a public order with their
+/// own key.
+async fn verify_transport_keys(rpc: &TonicRpcClient, mailbox: &mut Mailbox) {
+    let proposals = mailbox.proposals();
+    if proposals.is_empty() {
+        return;
+    }
+    let note_ids: Vec<NoteId> = proposals.keys().copied().collect();
+    let notes = match rpc.get_notes_by_id(&note_ids).await {
+        Ok(notes) => notes,
+        Err(e) => {
+            eprintln!(
+                "Failed to fetch the notes of the proposed transport keys: {}",
+                e
+            );
+            return;
+        }
+    };
 
+    for note in notes {
+        match note {
+            NetworkNote::Private(id, metadata, _)
+                if proposals.get(&id) == Some(&metadata.sender()) =>
+            {
+                if !mailbox.confirm(id) {
+                    eprintln!(
+                        "Kept the registered transport key of {}, notes are sealed to it",
+                        metadata.sender().to_hex()
+                    );
+                }
+            }
+            note => mailbox.withdraw(note.id()),
+        }
+    }
+}
+
+/// Reads the reference prices of a price file, see [StaticPrices::parse]
+fn load_prices(path: impl AsRef<Path>) -> anyhow::Result<StaticPrices> {
+    let text = std::fs::read_to_string(path)?;
+    Ok(StaticPrices::parse(&text)?)
+}
+
+/// Forwards the reference prices written to stdin to the matching loop
+async fn read_stdin_prices(sender: mpsc::Sender<Command>) {
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
     loop {
-        let (mut socket, _) = listener.accept().await?;
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => return,
+            Err(e) => {
+                eprintln!("Failed to read prices: {}", e);
+                return;
+            }
+        };
+        match parse_price_line(&line) {
+            Ok(Some((pair, price))) => {
+                if sender
+                    .send(Command::ReferencePrice { pair, price })
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+}
 
-        tokio::spawn(async move {
-            let mut len_buf = [0u8; 4];
-            if socket.read_exact(&mut len_buf).await.is_err() {
-                eprintln!("Failed to read length");
+async fn handle_connection(
+    mut socket: TcpStream,
+    sender: mpsc::Sender<Command>,
+    attestor: Arc<Attestor<MockProvider>>,
+) {
+    let mut request = match read_message::<_, MatcherRequest>(&mut socket).await {
+        Ok(request) => request,
+        Err(e) => {
+            eprintln!("Failed to read request: {}", e);
+            return;
+        }
+    };
+
+    // the attestation handshake precedes the request sealed to the attested key
+    if let MatcherRequest::Attest { nonce } = request {
+        let response = MatcherResponse::Attestation(attestor.report(nonce));
+        if let Err(e) = write_message(&mut socket, &response).await {
+            eprintln!("Failed to send attestation report: {}", e);
+            return;
+        }
+        request = match read_message::<_, MatcherRequest>(&mut socket).await {
+            Ok(request) => request,
+            Err(e) => {
+                eprintln!("Failed to read request: {}", e);
                 return;
             }
+        };
+    }
+
+    match request {
+        MatcherRequest::SubmitOrder {
+            note,
+            transport_key,
+        } => {
+            // the order itself is validated by the matcher core
+            let received_note = match Note::read_from_bytes(&note.payload) {
+                Ok(received_note) => received_note,
+                Err(e) => {
+                    eprintln!("Failed to deserialize note: {}", e);
+                    return;
+                }
+            };
+
+            println!("Received note:");
+            println!("  ID: {:?}", note.id);
+
+            let _ = sender
+                .send(Command::SubmitOrder {
+                    note: Box::new(received_note),
+                    transport_key,
+                })
+                .await;
+        }
+
+        MatcherRequest::SubmitSealedOrder {
+            sealed_note,
+            transport_key,
+        } => {
+            let received_note = match attestor.transport_key().open(&sealed_note) {
+                Ok(payload) => match Note::read_from_bytes(&payload) {
+                    Ok(received_note) => received_note,
+                    Err(e) => {
+                        eprintln!("Failed to deserialize note: {}", e);
+                        return;
+                    }
+                },
+                Err(e) => {
+                    eprintln!("Failed to open sealed order: {}", e);
+                    return;
+                }
+            };
+
+            println!("Received sealed note:");
+            println!("  ID: {:?}", received_note.id().to_hex());
+
+            let _ = sender
+                .send(Command::SubmitOrder {
+                    note: Box::new(received_note),
+                    transport_key,
+                })
+                .await;
+        }
+
+        MatcherRequest::Attest { .. } => eprintln!("Repeated attestation request"),
 
-            let len = u32::from_be_bytes(len_buf) as usize;
-            let mut buffer = vec![0u8; len];
+        MatcherRequest::AnswerChallenge { .. } => eprintln!("Unexpected key challenge answer"),
 
-            if socket.read_exact(&mut buffer).await.is_err() {
-                eprintln!("Failed to read payload");
+        MatcherRequest::AckNotes { .. } => eprintln!("Unexpected acknowledgement of notes"),
+
+        MatcherRequest::FetchNotes { account_id } => {
+            let account_id = match AccountId::from_hex(&account_id) {
+                Ok(account_id) => account_id,
+                Err(e) => {
+                    eprintln!("Invalid account id: {}", e);
+                    return;
+                }
+            };
+
+            let (reply, challenge) = oneshot::channel();
+            if sender
+                .send(Command::FetchChallenge { account_id, reply })
+                .await
+                .is_err()
+            {
                 return;
             }
 
-            match bincode::deserialize::<MidenNote>(&buffer) {
-                Ok(note) => {
-                    let note_bytes = note.payload;
-
-                    //check for valid notes
-                    //  1. check that note can be correctly deserialized
-                    //  2. check that note script hash is correct
-                    let received_note = Note::read_from_bytes(&note_bytes);
-                    if received_note.is_err() {
-                        eprintln!("Failed to deserialize note");
-                    };
-                    let received_note = received_note.unwrap();
-                    let note_script = received_note.script().to_bytes();
-
-                    let mut hasher = Sha256::new();
-                    hasher.update(note_script);
-                    let hash = hasher.finalize().to_hex();
-                    println!("script hash: {:?}", hash);
-
-                    // note script hash of PRIVATE_SWAPp note
-                    if hash != "e39a29af05b233279c0009701242ff54b1d8c0d848ad2f2001eb7e0ac6ef745e" {
-                        eprintln!("Not a valid note");
+            // without a transport key no note was delivered to the account
+            let (challenge, sealed_secret) = match challenge.await {
+                Ok(Some(challenge)) => challenge,
+                Ok(None) => {
+                    let response = MatcherResponse::Notes(Vec::new());
+                    if let Err(e) = write_message(&mut socket, &response).await {
+                        eprintln!("Failed to send notes: {}", e);
                     }
+                    return;
+                }
+                Err(_) => return,
+            };
+
+            let response = MatcherResponse::KeyChallenge(sealed_secret);
+            if let Err(e) = write_message(&mut socket, &response).await {
+                eprintln!("Failed to send key challenge: {}", e);
+                return;
+            }
+            let secret = match read_message(&mut socket).await {
+                Ok(MatcherRequest::AnswerChallenge { secret }) => secret,
+                Ok(_) => {
+                    eprintln!("Expected the answer to the key challenge");
+                    return;
+                }
+                Err(e) => {
+                    eprintln!("Failed to read request: {}", e);
+                    return;
+                }
+            };
 
-                    println!("Received note:");
-                    println!("  ID: {:?}", note.id);
-                    // println!("  Payload: {:#?}", received_note);
+            let (reply, notes) = oneshot::channel();
+            if sender
+                .send(Command::FetchNotes {
+                    challenge: challenge.clone(),
+                    secret: secret.clone(),
+                    reply,
+                })
+                .await
+                .is_err()
+            {
+                return;
+            }
+            let notes = match notes.await {
+                Ok(Some(notes)) => notes,
+                _ => {
+                    eprintln!("Key challenge of {} failed", account_id.to_hex());
+                    return;
+                }
+            };
+
+            if let Err(e) = write_message(&mut socket, &MatcherResponse::Notes(notes)).await {
+                eprintln!("Failed to send notes: {}", e);
+                return;
+            }
+
+            // the notes stay in the mailbox until the client acknowledges their import
+            let ephemeral_keys = match read_message(&mut socket).await {
+                Ok(MatcherRequest::AckNotes { ephemeral_keys }) => ephemeral_keys,
+                Ok(_) => {
+                    eprintln!("Expected the acknowledgement of the notes");
+                    return;
+                }
+                Err(e) => {
+                    eprintln!("Failed to read request: {}", e);
+                    return;
+                }
+            };
+            let _ = sender
+                .send(Command::AckNotes {
+                    challenge,
+                    secret,
+                    ephemeral_keys,
+                })
+                .await;
+        }
+
+        MatcherRequest::QueryMarket {
+            faucet_a,
+            faucet_b,
+            tick,
+        } => {
+            let pair = match (
+                AccountId::from_hex(&faucet_a),
+                AccountId::from_hex(&faucet_b),
+            ) {
+                (Ok(faucet_a), Ok(faucet_b)) => TradingPair::new(faucet_a, faucet_b),
+                (Err(e), _) | (_, Err(e)) => {
+                    eprintln!("Invalid faucet id: {}", e);
+                    return;
+                }
+            };
+
+            let (reply, summary) = oneshot::channel();
+            if sender
+                .send(Command::QueryMarket { pair, tick, reply })
+                .await
+                .is_err()
+            {
+                return;
+            }
+            let Ok(summary) = summary.await else {
+                return;
+            };
+
+            if let Err(e) = write_message(&mut socket, &MatcherResponse::Market(summary)).await {
+                eprintln!("Failed to send market summary: {}", e);
+            }
+        }
+
+        MatcherRequest::Subscribe => {
+            let (reply, subscription) = oneshot::channel();
+            if sender.send(Command::Subscribe { reply }).await.is_err() {
+                return;
+            }
+            let Ok((mut receiver, snapshots)) = subscription.await else {
+                return;
+            };
+
+            for event in snapshots {
+                if write_message(&mut socket, &MatcherResponse::Event(event))
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+            }
+
+            loop {
+                let event = match receiver.recv().await {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        eprintln!("Subscriber lagging, skipped {} events", skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                };
+                // the subscriber disconnected
+                if write_message(&mut socket, &MatcherResponse::Event(event))
+                    .await
+                    .is_err()
+                {
+                    return;
                 }
-                Err(e) => eprintln!("Failed to deserialize note: {}", e),
             }
-        });
+        }
     }
 }
@@ -0,0 +1,34 @@
+pub use miden_dark_pool_core::events::*;
+
+use tokio::sync::broadcast;
+
+/// Number of events a subscriber can fall behind before it starts missing events
+pub const EVENT_BUFFER: usize = 256;
+
+/// Fan out of [MarketEvent]s to the subscribers of the matcher
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<MarketEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER);
+        Self { sender }
+    }
+
+    /// Sends `event` to the current subscribers, events without subscribers are dropped
+    pub fn publish(&self, event: MarketEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<MarketEvent> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
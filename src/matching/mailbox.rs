@@ -0,0 +1,163 @@
+use crate::utils::transport::{SealedNote, seal};
+
+use miden_client::account::AccountId;
+use miden_client::note::{NoteFile, NoteId};
+use miden_objects::utils::Serializable;
+use rand::Rng;
+use std::collections::BTreeMap;
+
+/// Out of band delivery of private notes produced by settlements
+///
+/// Private P2ID and SWAPp' notes are only known to the executor. The mailbox seals every note
+/// to the transport key its recipient announced when submitting an order, and keeps it until
+/// the recipient proves it holds the key, see [Mailbox::challenge].
+///
+/// Orders are not authenticated when submitted, so the announced key is only proposed, see
+/// [Mailbox::propose]. Notes for an account whose key is not confirmed yet are held serialized.
+#[derive(Debug, Default)]
+pub struct Mailbox {
+    transport_keys: BTreeMap<AccountId, [u8; 32]>,
+    proposed_keys: BTreeMap<NoteId, (AccountId, [u8; 32])>,
+    held: BTreeMap<AccountId, Vec<Vec<u8>>>,
+    pending: BTreeMap<AccountId, Vec<SealedNote>>,
+}
+
+/// Random secret sealed to the transport key of an account, opening it proves the key is held
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyChallenge {
+    account_id: AccountId,
+    transport_key: [u8; 32],
+    secret: [u8; 32],
+}
+
+impl Mailbox {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the key announced for `account_id` with the order `note_id`
+    ///
+    /// The key is only used once [Mailbox::confirm] is called for the order, after its note is
+    /// found on chain as a private note sent by `account_id`.
+    pub fn propose(&mut self, note_id: NoteId, account_id: AccountId, transport_key: [u8; 32]) {
+        self.proposed_keys
+            .insert(note_id, (account_id, transport_key));
+    }
+
+    /// Orders with a proposed key and the account they were submitted for
+    pub fn proposals(&self) -> BTreeMap<NoteId, AccountId> {
+        self.proposed_keys
+            .iter()
+            .map(|(note_id, (account_id, _))| (*note_id, *account_id))
+            .collect()
+    }
+
+    /// Drops the key proposed with `note_id`, e.g. when the order left the book unfilled
+    pub fn withdraw(&mut self, note_id: NoteId) {
+        self.proposed_keys.remove(&note_id);
+    }
+
+    /// Registers the key proposed with `note_id` and seals the notes held for its account
+    ///
+    /// The first key confirmed for an account is kept. Returns false if there is no proposal
+    /// for `note_id` or another key is already registered.
+    pub fn confirm(&mut self, note_id: NoteId) -> bool {
+        let Some((account_id, transport_key)) = self.proposed_keys.remove(&note_id) else {
+            return false;
+        };
+        let registered = *self
+            .transport_keys
+            .entry(account_id)
+            .or_insert(transport_key);
+
+        for note_bytes in self.held.remove(&account_id).unwrap_or_default() {
+            let sealed = seal(registered, &note_bytes);
+            self.pending.entry(account_id).or_default().push(sealed);
+        }
+        registered == transport_key
+    }
+
+    /// Seals `note_file` for `account_id`, or holds it until a key proposed for the account is
+    /// confirmed. Returns false if the account has no transport key.
+    pub fn deliver(&mut self, account_id: AccountId, note_file: &NoteFile) -> bool {
+        let Some(transport_key) = self.transport_keys.get(&account_id) else {
+            if !self
+                .proposed_keys
+                .values()
+                .any(|(proposer, _)| *proposer == account_id)
+            {
+                return false;
+            }
+            self.held
+                .entry(account_id)
+                .or_default()
+                .push(note_file.to_bytes());
+            return true;
+        };
+
+        let sealed = seal(*transport_key, &note_file.to_bytes());
+        self.pending.entry(account_id).or_default().push(sealed);
+        true
+    }
+
+    /// Challenge for the transport key of `account_id` and its secret sealed to the key,
+    /// `None` if the account has no transport key
+    pub fn challenge(&self, account_id: AccountId) -> Option<(KeyChallenge, SealedNote)> {
+        let transport_key = *self.transport_keys.get(&account_id)?;
+        let secret: [u8; 32] = rand::rng().random();
+
+        let challenge = KeyChallenge {
+            account_id,
+            transport_key,
+            secret,
+        };
+        Some((challenge, seal(transport_key, &secret)))
+    }
+
+    /// Returns all the notes waiting for the account of `challenge`
+    ///
+    /// The notes stay in the mailbox until their import is acknowledged, see
+    /// [Mailbox::acknowledge]. Returns `None` unless `response` is the opened secret of
+    /// `challenge` and the challenged key is still the key of the account.
+    pub fn peek(&self, challenge: &KeyChallenge, response: &[u8]) -> Option<Vec<SealedNote>> {
+        if !self.is_answered(challenge, response) {
+            return None;
+        }
+        Some(
+            self.pending
+                .get(&challenge.account_id)
+                .cloned()
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Removes the notes of the account of `challenge` sealed with one of `ephemeral_keys`,
+    /// once their recipient imported them
+    ///
+    /// Returns false without removing anything unless `response` answers `challenge`, see
+    /// [Mailbox::peek].
+    pub fn acknowledge(
+        &mut self,
+        challenge: &KeyChallenge,
+        response: &[u8],
+        ephemeral_keys: &[[u8; 32]],
+    ) -> bool {
+        if !self.is_answered(challenge, response) {
+            return false;
+        }
+        if let Some(pending) = self.pending.get_mut(&challenge.account_id) {
+            pending.retain(|sealed| !ephemeral_keys.contains(&sealed.ephemeral_key));
+            if pending.is_empty() {
+                self.pending.remove(&challenge.account_id);
+            }
+        }
+        true
+    }
+
+    // true if `response` is the opened secret of `challenge` and the challenged key is still
+    // the key of the account
+    fn is_answered(&self, challenge: &KeyChallenge, response: &[u8]) -> bool {
+        self.transport_keys.get(&challenge.account_id) == Some(&challenge.transport_key)
+            && response == challenge.secret
+    }
+}
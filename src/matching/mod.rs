@@ -0,0 +1,5 @@
+pub use miden_dark_pool_core::{auction, book, engine, market, oracle, ring};
+
+pub mod events;
+pub mod mailbox;
+pub mod settlement;
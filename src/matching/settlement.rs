@@ -0,0 +1,150 @@
+use crate::matching::book::SwapFill;
+use crate::matching::engine::SettlementPlan;
+use crate::utils::common::{
+    SwapNoteError, create_swap_note_successor, reconstruct_fee_note, reconstruct_p2id_note,
+};
+
+use miden_client::account::AccountId;
+use miden_client::note::{Note, NoteExecutionHint, NoteFile, NoteMetadata, NoteTag, NoteType};
+use miden_client::transaction::{TransactionRequestBuilder, TransactionRequestError};
+use miden_client::{Client, ClientError};
+use miden_objects::note::NoteDetails;
+use miden_objects::{Felt, NoteError, ZERO};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SettlementError {
+    #[error("client error: {0}")]
+    Client(#[from] ClientError),
+
+    #[error("invalid transaction request: {0}")]
+    TransactionRequest(#[from] TransactionRequestError),
+
+    #[error("swap note error: {0}")]
+    SwapNote(#[from] SwapNoteError),
+
+    #[error("note error: {0}")]
+    Note(#[from] NoteError),
+}
+
+/// Outcome of the transaction settling a [SettlementPlan]
+pub struct Settlement {
+    /// Notes each order creator is entitled to (P2ID payout and SWAPp' if partially filled),
+    /// and the fee P2ID notes of their fee recipients
+    pub deliveries: Vec<(AccountId, NoteFile)>,
+
+    /// SWAPp' of every order in the order of [SettlementPlan::fills], as expected by
+    /// [Input::Settled](miden_dark_pool_core::engine::Input::Settled). `None` for the orders
+    /// that were completely filled.
+    pub successors: Vec<Option<Note>>,
+}
+
+/// Consumes every order of `plan` with the executor account in a single transaction
+///
+/// The P2ID and SWAPp' notes created by the note scripts are private, so they are passed as
+/// expected output notes to get their full details back for delivery.
+pub async fn settle_plan(
+    client: &mut Client,
+    executor: AccountId,
+    plan: &SettlementPlan,
+) -> Result<Settlement, SettlementError> {
+    let (deliveries, successors) = settle_fills(client, executor, &plan.fills()).await?;
+
+    Ok(Settlement {
+        deliveries,
+        successors,
+    })
+}
+
+/// Consumes the orders of `fills` in order, returns the deliveries and the SWAPp' of every order
+async fn settle_fills(
+    client: &mut Client,
+    executor: AccountId,
+    fills: &[&SwapFill],
+) -> Result<(Vec<(AccountId, NoteFile)>, Vec<Option<Note>>), SettlementError> {
+    let mut input_notes = Vec::new();
+    let mut expected_notes = Vec::new();
+    let mut deliveries = Vec::new();
+    let mut successors = Vec::new();
+
+    for fill in fills {
+        let note_type = fill.order.note.metadata().note_type();
+        let note_args = [
+            ZERO,
+            ZERO,
+            Felt::new(fill.improvement),
+            Felt::new(fill.filled_amount),
+        ];
+        input_notes.push((fill.order.note.clone(), Some(note_args)));
+
+        let (details, tag) = reconstruct_p2id_note(&fill.order.note, 0, fill.paid())?;
+        let payout = create_p2id_note(executor, note_type, details, tag)?;
+        expected_notes.push(payout.clone());
+        deliveries.push((fill.order.creator(), payout));
+
+        if let Some((fee_recipient, details, tag)) =
+            reconstruct_fee_note(&fill.order.note, 0, fill.paid())?
+        {
+            let fee_note = create_p2id_note(executor, note_type, details, tag)?;
+            expected_notes.push(fee_note.clone());
+            deliveries.push((fee_recipient, fee_note));
+        }
+
+        if let Some(successor) = create_swap_note_successor(
+            &fill.order.note,
+            executor,
+            fill.filled_amount,
+            fill.amount_out,
+        )? {
+            expected_notes.push(successor.clone());
+            deliveries.push((fill.order.creator(), successor.clone()));
+            successors.push(Some(successor));
+        } else {
+            successors.push(None);
+        }
+    }
+
+    let transaction_request = TransactionRequestBuilder::new()
+        .with_unauthenticated_input_notes(input_notes)
+        .with_expected_output_notes(expected_notes)
+        .build()?;
+
+    let tx_execution_result = client
+        .new_transaction(executor, transaction_request)
+        .await?;
+    client.submit_transaction(tx_execution_result).await?;
+
+    let after_block_num = client.get_sync_height().await?;
+    let deliveries = deliveries
+        .into_iter()
+        .map(|(account_id, note)| {
+            let tag = note.metadata().tag();
+            let note_file = NoteFile::NoteDetails {
+                details: note.into(),
+                after_block_num,
+                tag: Some(tag),
+            };
+            (account_id, note_file)
+        })
+        .collect();
+
+    Ok((deliveries, successors))
+}
+
+/// Builds a P2ID note emitted by the SWAPp script when consumed by the executor
+///
+/// Public SWAPp orders emit public P2ID notes, private orders private ones.
+fn create_p2id_note(
+    executor: AccountId,
+    note_type: NoteType,
+    details: NoteDetails,
+    tag: NoteTag,
+) -> Result<Note, SwapNoteError> {
+    let metadata = NoteMetadata::new(executor, note_type, tag, NoteExecutionHint::always(), ZERO)?;
+
+    Ok(Note::new(
+        details.assets().clone(),
+        metadata,
+        details.recipient().clone(),
+    ))
+}
@@ -1,13 +1,13 @@
-use bincode;
-use tokio::io::AsyncWriteExt;
 use tokio::net::TcpStream;
 
 use miden_lib::utils::Serializable;
 
-mod cli;
-mod utils;
 use clap::Parser;
-use utils::common::MidenNote;
+use miden_dark_pool::cli;
+use miden_dark_pool::utils::attestation::attest_matcher;
+use miden_dark_pool::utils::common::MidenNote;
+use miden_dark_pool::utils::protocol::{MATCHER_ADDRESS, MatcherRequest, write_message};
+use miden_dark_pool::utils::transport::{TRANSPORT_KEYS_DIR, TransportKey, seal};
 
 #[derive(Parser, Debug)]
 #[command(name = "miden-cli", about = "Dark pool CLI")]
@@ -20,6 +20,21 @@ pub enum Cli {
 
     #[command(name = "consume-swapped")]
     ConsumeSwapped(cli::consume_swapped::ConsumeSwapped),
+
+    #[command(name = "recover-payout")]
+    RecoverPayout(cli::recover_payout::RecoverPayout),
+
+    #[command(name = "fetch-notes")]
+    FetchNotes(cli::fetch_notes::FetchNotes),
+
+    #[command(name = "market")]
+    Market(cli::market::Market),
+
+    #[command(name = "subscribe")]
+    Subscribe(cli::subscribe::Subscribe),
+
+    #[command(name = "account", subcommand)]
+    Account(cli::account::AccountCommand),
 }
 
 #[tokio::main]
@@ -28,22 +43,40 @@ async fn main() -> anyhow::Result<()> {
 
     match cli {
         Cli::OpenOrder(cmd) => {
-            let swap_note = cmd.run().await?;
-            let buffer = swap_note.to_bytes();
+            let verifier = cmd.verifier()?;
 
-            let note = MidenNote {
-                id: swap_note.id().to_hex(),
-                payload: buffer,
+            //TODO: right now simple tcp but encryption needs to added.
+            // we can also consider some other communication protocol such as QUIC
+            let mut stream = TcpStream::connect(MATCHER_ADDRESS).await?;
+
+            // the matcher is attested before the order goes on chain, so a matcher failing the
+            // attestation never gets an order to fill. Its transport key changes when it restarts
+            let matcher_key = match &verifier {
+                Some(verifier) => Some(attest_matcher(&mut stream, verifier).await?),
+                None => None,
             };
 
-            let encoded = bincode::serialize(&note)?;
-            let length = (encoded.len() as u32).to_be_bytes();
+            let swap_note = cmd.run().await?;
+            let buffer = swap_note.to_bytes();
 
-            //TODO: right now simple tcp but encryption needs to added.
-            // we can also consider some other communication protocol such as QUIC
-            let mut stream = TcpStream::connect("127.0.0.1:8080").await?;
-            stream.write_all(&length).await?;
-            stream.write_all(&encoded).await?;
+            // notes resulting from fills are sealed to this key by the matcher
+            let transport_key =
+                TransportKey::load_or_create(TRANSPORT_KEYS_DIR, swap_note.metadata().sender())?;
+
+            let request = match matcher_key {
+                Some(matcher_key) => MatcherRequest::SubmitSealedOrder {
+                    sealed_note: seal(matcher_key, &buffer),
+                    transport_key: transport_key.public_key(),
+                },
+                None => MatcherRequest::SubmitOrder {
+                    note: MidenNote {
+                        id: swap_note.id().to_hex(),
+                        payload: buffer,
+                    },
+                    transport_key: transport_key.public_key(),
+                },
+            };
+            write_message(&mut stream, &request).await?;
 
             println!("Note sent");
             println!("Note id: {}", swap_note.id().to_hex());
@@ -58,6 +91,30 @@ async fn main() -> anyhow::Result<()> {
             let result = cmd.run().await?;
             println!("{}", result);
         }
+
+        Cli::RecoverPayout(cmd) => {
+            let note_id = cmd.run().await?;
+            println!("{}", note_id.to_hex());
+        }
+
+        Cli::FetchNotes(cmd) => {
+            let imported = cmd.run().await?;
+            println!("{} notes imported", imported);
+        }
+
+        Cli::Market(cmd) => {
+            let summary = cmd.run().await?;
+            println!("{:#?}", summary);
+        }
+
+        Cli::Subscribe(cmd) => {
+            let received = cmd.run().await?;
+            println!("{} events received", received);
+        }
+
+        Cli::Account(cmd) => {
+            cmd.run().await?;
+        }
     }
 
     Ok(())
@@ -0,0 +1,235 @@
+use crate::utils::protocol::{
+    MatcherRequest, MatcherResponse, ProtocolError, read_message, write_message,
+};
+use crate::utils::transport::TransportKey;
+
+use miden_objects::utils::{HexParseError, bytes_to_hex_string, hex_to_bytes};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+use std::path::Path;
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+// key of the mock provider, public so anyone can produce a mock report
+const MOCK_SIGNING_KEY: &[u8] = b"miden-dark-pool mock attestation";
+
+#[derive(Error, Debug)]
+pub enum AttestationError {
+    #[error("matcher communication error: {0}")]
+    Protocol(#[from] ProtocolError),
+
+    #[error("invalid measurement: {0}")]
+    InvalidMeasurement(#[from] HexParseError),
+
+    #[error("attestation report signature is invalid")]
+    InvalidSignature,
+
+    #[error("attestation report is not bound to the transport key of the matcher and the nonce")]
+    KeyNotBound,
+
+    #[error("measurement {0} is not an expected measurement")]
+    UnexpectedMeasurement(String),
+
+    #[error("mock attestation reports are not accepted")]
+    MockNotAllowed,
+}
+
+/// Attestation providers a report can come from
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderKind {
+    /// Software provider without any hardware root of trust, see [MockProvider]
+    Mock,
+}
+
+/// Report presented by the matcher in the attestation handshake
+///
+/// The provider signs the measurement of the code running the matcher together with the report
+/// data, which commits to the transport key of the matcher and to the nonce of the user. Orders
+/// sealed to the transport key can then only be opened by the measured code.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AttestationReport {
+    pub provider: ProviderKind,
+    pub measurement: [u8; 32],
+    /// x25519 key orders are sealed to
+    pub transport_key: [u8; 32],
+    /// sha256(transport_key || nonce)
+    pub report_data: [u8; 32],
+    pub signature: Vec<u8>,
+}
+
+/// Source of attestation reports, e.g. the quoting service of a TEE
+pub trait AttestationProvider {
+    fn kind(&self) -> ProviderKind;
+
+    /// Measurement of the code the reports are produced for
+    fn measurement(&self) -> [u8; 32];
+
+    /// Signs `report_data` together with the measurement
+    fn sign(&self, report_data: [u8; 32]) -> Vec<u8>;
+}
+
+/// Attestation provider running in software, so the handshake can be used outside of a TEE
+///
+/// The signing key is public: a mock report proves nothing about the code running the matcher
+/// and is only accepted by a [Verifier] allowing mock reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MockProvider {
+    measurement: [u8; 32],
+}
+
+impl MockProvider {
+    pub fn new(measurement: [u8; 32]) -> Self {
+        Self { measurement }
+    }
+
+    /// Measures the executable at `path`, the sha256 of its content
+    pub fn measure(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let code = std::fs::read(path)?;
+        Ok(Self::new(sha2::Sha256::digest(code).into()))
+    }
+}
+
+impl AttestationProvider for MockProvider {
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Mock
+    }
+
+    fn measurement(&self) -> [u8; 32] {
+        self.measurement
+    }
+
+    fn sign(&self, report_data: [u8; 32]) -> Vec<u8> {
+        mock_signature(&self.measurement, &report_data).to_vec()
+    }
+}
+
+/// Transport key of the matcher and the provider attesting it
+pub struct Attestor<P> {
+    transport_key: TransportKey,
+    provider: P,
+}
+
+impl<P: AttestationProvider> Attestor<P> {
+    pub fn new(transport_key: TransportKey, provider: P) -> Self {
+        Self {
+            transport_key,
+            provider,
+        }
+    }
+
+    pub fn transport_key(&self) -> &TransportKey {
+        &self.transport_key
+    }
+
+    /// Report binding the transport key to the measurement of the provider for `nonce`
+    pub fn report(&self, nonce: [u8; 32]) -> AttestationReport {
+        let transport_key = self.transport_key.public_key();
+        let report_data = report_data(&transport_key, &nonce);
+
+        AttestationReport {
+            provider: self.provider.kind(),
+            measurement: self.provider.measurement(),
+            transport_key,
+            report_data,
+            signature: self.provider.sign(report_data),
+        }
+    }
+}
+
+/// Checks attestation reports against a list of expected measurements
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Verifier {
+    expected: Vec<[u8; 32]>,
+    allow_mock: bool,
+}
+
+impl Verifier {
+    pub fn new(expected: Vec<[u8; 32]>) -> Self {
+        Self {
+            expected,
+            allow_mock: false,
+        }
+    }
+
+    /// Verifier of the measurements given as 0x prefixed hex strings
+    pub fn from_hex(expected: &[impl AsRef<str>]) -> Result<Self, AttestationError> {
+        let expected = expected
+            .iter()
+            .map(|measurement| hex_to_bytes(measurement.as_ref()))
+            .collect::<Result<_, _>>()?;
+        Ok(Self::new(expected))
+    }
+
+    /// Accepts the reports of the [MockProvider], for testing outside of a TEE
+    pub fn allow_mock(mut self, allow_mock: bool) -> Self {
+        self.allow_mock = allow_mock;
+        self
+    }
+
+    /// Returns the attested transport key if `report` is signed by its provider, answers
+    /// `nonce` and comes from an expected measurement
+    pub fn verify(
+        &self,
+        report: &AttestationReport,
+        nonce: [u8; 32],
+    ) -> Result<[u8; 32], AttestationError> {
+        match report.provider {
+            ProviderKind::Mock if !self.allow_mock => return Err(AttestationError::MockNotAllowed),
+            ProviderKind::Mock => {
+                let signature = mock_signature(&report.measurement, &report.report_data);
+                if report.signature != signature {
+                    return Err(AttestationError::InvalidSignature);
+                }
+            }
+        }
+
+        if report.report_data != report_data(&report.transport_key, &nonce) {
+            return Err(AttestationError::KeyNotBound);
+        }
+        if !self.expected.contains(&report.measurement) {
+            return Err(AttestationError::UnexpectedMeasurement(
+                bytes_to_hex_string(report.measurement),
+            ));
+        }
+
+        Ok(report.transport_key)
+    }
+}
+
+/// Requests the attestation report of the matcher on `stream` and verifies it with a fresh
+/// nonce, returns the attested transport key of the matcher
+///
+/// The connection stays open for the request sealed to the key.
+pub async fn attest_matcher<S>(
+    stream: &mut S,
+    verifier: &Verifier,
+) -> Result<[u8; 32], AttestationError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let nonce: [u8; 32] = rand::rng().random();
+    write_message(stream, &MatcherRequest::Attest { nonce }).await?;
+
+    let MatcherResponse::Attestation(report) = read_message(stream).await? else {
+        return Err(ProtocolError::UnexpectedResponse.into());
+    };
+    verifier.verify(&report, nonce)
+}
+
+/// sha256(transport_key || nonce)
+fn report_data(transport_key: &[u8; 32], nonce: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = sha2::Sha256::new();
+    hasher.update(transport_key);
+    hasher.update(nonce);
+    hasher.finalize().into()
+}
+
+/// sha256(MOCK_SIGNING_KEY || measurement || report_data)
+fn mock_signature(measurement: &[u8; 32], report_data: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = sha2::Sha256::new();
+    hasher.update(MOCK_SIGNING_KEY);
+    hasher.update(measurement);
+    hasher.update(report_data);
+    hasher.finalize().into()
+}
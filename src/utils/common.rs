@@ -2,18 +2,41 @@ use miden_client::{
     Client, ClientError, account::Account, account::AccountId, builder::ClientBuilder,
     rpc::Endpoint, rpc::TonicRpcClient,
 };
+use miden_client::{
+    account::component::{BasicWallet, RpoFalcon512},
+    account::{AccountBuilder, AccountStorageMode, AccountType},
+    auth::AuthSecretKey,
+    crypto::SecretKey,
+    keystore::{FilesystemKeyStore, KeyStoreError},
+};
 
+use rand::RngCore;
+use rand::rngs::StdRng;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use thiserror::Error;
 
-use miden_lib::{note::utils::build_swap_tag, transaction::TransactionKernel};
-use miden_objects::asset::FungibleAsset;
-use miden_objects::note::{
-    Note, NoteAssets, NoteExecutionHint, NoteExecutionMode, NoteInputs, NoteMetadata,
-    NoteRecipient, NoteScript, NoteTag, NoteType,
-};
-use miden_objects::{Felt, NoteError, Word, asset::Asset};
-use miden_vm::Assembler;
+use miden_objects::{AccountError, AccountIdError};
+
+pub use miden_dark_pool_core::common::*;
+
+#[derive(Error, Debug)]
+pub enum AccountSetupError {
+    #[error("client error: {0}")]
+    Client(#[from] ClientError),
+
+    #[error("keystore error: {0}")]
+    KeyStore(#[from] KeyStoreError),
+
+    #[error("account error: {0}")]
+    Account(#[from] AccountError),
+
+    #[error("invalid anchor block: {0}")]
+    Anchor(#[from] AccountIdError),
+}
+
+pub const STORE_PATH: &str = "./store.sqlite3";
+pub const KEYSTORE_PATH: &str = "./keystore";
 
 // the payload vector is the serialized note
 // id is the noteId
@@ -24,19 +47,19 @@ pub struct MidenNote {
 }
 
 pub async fn client_setup() -> Result<Client, ClientError> {
-    // Initialize client & keystore
-    let endpoint = Endpoint::new(
-        "https".to_string(),
-        "rpc.testnet.miden.io".to_string(),
-        Some(443),
-    );
-    let timeout_ms = 10_000;
-    let rpc_api = Arc::new(TonicRpcClient::new(&endpoint, timeout_ms));
+    client_setup_with_store(STORE_PATH, KEYSTORE_PATH).await
+}
 
+/// Same as [client_setup] but with a custom sqlite store and keystore location
+pub async fn client_setup_with_store(
+    store_path: &str,
+    keystore_path: &str,
+) -> Result<Client, ClientError> {
+    // Initialize client & keystore
     let mut client = ClientBuilder::new()
-        .with_rpc(rpc_api)
-        .with_filesystem_keystore("./keystore")
-        .with_sqlite_store("./store.sqlite3")
+        .with_rpc(rpc_setup())
+        .with_filesystem_keystore(keystore_path)
+        .with_sqlite_store(store_path)
         .in_debug_mode(true)
         .build()
         .await?;
@@ -46,6 +69,17 @@ pub async fn client_setup() -> Result<Client, ClientError> {
     Ok(client)
 }
 
+/// RPC client of the node the clients of [client_setup_with_store] connect to
+pub fn rpc_setup() -> Arc<TonicRpcClient> {
+    let endpoint = Endpoint::new(
+        "https".to_string(),
+        "rpc.testnet.miden.io".to_string(),
+        Some(443),
+    );
+    let timeout_ms = 10_000;
+    Arc::new(TonicRpcClient::new(&endpoint, timeout_ms))
+}
+
 pub(crate) async fn get_account(
     client: &mut Client,
     acc_id: AccountId,
@@ -56,72 +90,39 @@ pub(crate) async fn get_account(
 
     let account = binding.account();
 
-    return Ok(account.clone());
+    Ok(account.clone())
 }
 
-/// Generates a SWAP note - swap of assets between two accounts
-pub fn create_partial_swap_note(
-    creator: AccountId,
-    last_consumer: AccountId,
-    offered_asset: Asset,
-    requested_asset: Asset,
-    swap_serial_num: [Felt; 4],
-    fill_number: u64,
-) -> Result<Note, NoteError> {
-    let assembler: Assembler = TransactionKernel::assembler().with_debug_mode(true);
-
-    let note_code = include_str!("../../notes/PRIVATE_SWAPp.masm");
-    let note_script = NoteScript::compile(note_code, assembler).unwrap();
-    let note_type = NoteType::Private;
-
-    let requested_asset_word: Word = requested_asset.into();
-
-    let swapp_tag = get_tag(note_type, &offered_asset, &requested_asset)?;
-    let p2id_tag = NoteTag::from_account_id(creator, NoteExecutionMode::Local)?;
-
-    let inputs = NoteInputs::new(vec![
-        requested_asset_word[0],
-        requested_asset_word[1],
-        requested_asset_word[2],
-        requested_asset_word[3],
-        swapp_tag.inner().into(),
-        p2id_tag.into(),
-        Felt::new(0),
-        Felt::new(0),
-        Felt::new(fill_number),
-        Felt::new(0),
-        Felt::new(0),
-        Felt::new(0),
-        creator.prefix().into(),
-        creator.suffix().into(),
-    ])?;
-
-    let aux = Felt::new(0);
-
-    // build the outgoing note
-    let metadata = NoteMetadata::new(
-        last_consumer,
-        note_type,
-        swapp_tag,
-        NoteExecutionHint::always(),
-        aux,
-    )?;
-
-    let assets = NoteAssets::new(vec![offered_asset])?;
-    let recipient = NoteRecipient::new(swap_serial_num, note_script.clone(), inputs.clone());
-    let note = Note::new(assets.clone(), metadata, recipient.clone());
-
-    Ok(note)
-}
+/// Creates a basic wallet authenticated with RpoFalcon512
+/// The account is added to the client and its secret key to the keystore
+pub async fn create_wallet(
+    client: &mut Client,
+    keystore: &FilesystemKeyStore<StdRng>,
+    storage_mode: AccountStorageMode,
+) -> Result<Account, AccountSetupError> {
+    let mut init_seed = [0_u8; 32];
+    client.rng().fill_bytes(&mut init_seed);
+
+    let key_pair = SecretKey::with_rng(client.rng());
+
+    // Anchor block
+    let anchor_block = client.get_latest_epoch_block().await?;
+
+    // Build the account
+    let builder = AccountBuilder::new(init_seed)
+        .anchor((&anchor_block).try_into()?)
+        .account_type(AccountType::RegularAccountUpdatableCode)
+        .storage_mode(storage_mode)
+        .with_component(RpoFalcon512::new(key_pair.public_key()))
+        .with_component(BasicWallet);
+
+    let (account, seed) = builder.build()?;
+
+    // Add the account to the client
+    client.add_account(&account, Some(seed), false).await?;
+
+    // Add the key pair to the keystore
+    keystore.add_key(&AuthSecretKey::RpoFalcon512(key_pair))?;
 
-/// Generates a SWAP note tag
-/// build_swap_tag(note_type, asset1, asset2)
-/// where asset_{i} is an Asset created with AssetId of the asset pairs and 0 amount so that the tag is deterministic for a given asset pair
-fn get_tag(note_type: NoteType, asset1: &Asset, asset2: &Asset) -> Result<NoteTag, NoteError> {
-    let id1 = asset1.unwrap_fungible().faucet_id();
-    let id2 = asset2.unwrap_fungible().faucet_id();
-    let asset1 = FungibleAsset::new(id1, 0).unwrap();
-    let asset2 = FungibleAsset::new(id2, 0).unwrap();
-    let tag = build_swap_tag(note_type, &asset1.into(), &asset2.into())?;
-    Ok(tag)
+    Ok(account)
 }
@@ -0,0 +1,317 @@
+use crate::utils::common::{AccountSetupError, create_wallet};
+
+use miden_client::account::component::{BasicFungibleFaucet, RpoFalcon512};
+use miden_client::account::{Account, AccountBuilder, AccountId, AccountStorageMode, AccountType};
+use miden_client::asset::TokenSymbol;
+use miden_client::auth::AuthSecretKey;
+use miden_client::crypto::SecretKey;
+use miden_client::keystore::{FilesystemKeyStore, KeyStoreError};
+use miden_client::transaction::{TransactionRequestBuilder, TransactionRequestError};
+use miden_client::{Client, ClientError};
+use miden_objects::asset::FungibleAsset;
+use miden_objects::note::{NoteId, NoteType};
+use miden_objects::{AccountError, AccountIdError, AssetError, Felt};
+
+use rand::RngCore;
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Names given to the faucets and wallets created by the dev tooling
+pub const DEV_ACCOUNTS_PATH: &str = "./dev_accounts.yaml";
+
+/// How long [mint_and_consume] waits for the minted note to be committed
+const MINT_WAIT_SECS: u64 = 120;
+const MINT_POLL_SECS: u64 = 3;
+
+#[derive(Error, Debug)]
+pub enum DevError {
+    #[error("client error: {0}")]
+    Client(#[from] ClientError),
+
+    #[error("account setup error: {0}")]
+    AccountSetup(#[from] AccountSetupError),
+
+    #[error("keystore error: {0}")]
+    KeyStore(#[from] KeyStoreError),
+
+    #[error("account error: {0}")]
+    Account(#[from] AccountError),
+
+    #[error("invalid account id: {0}")]
+    AccountId(#[from] AccountIdError),
+
+    #[error("asset error: {0}")]
+    Asset(#[from] AssetError),
+
+    #[error("transaction request error: {0}")]
+    TransactionRequest(#[from] TransactionRequestError),
+
+    #[error("config error: {0}")]
+    Config(#[from] ConfigError),
+
+    #[error("mint transaction did not create a note")]
+    MissingMintNote,
+
+    #[error("minted note {0} was not committed after {MINT_WAIT_SECS} seconds")]
+    MintNoteNotCommitted(NoteId),
+}
+
+/// Errors of the scenario and dev accounts files
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("yaml error: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+
+    #[error("unknown account: {0}")]
+    UnknownAccount(String),
+
+    #[error("unknown token in scenario: {0}")]
+    UnknownToken(String),
+}
+
+/// Fungible token deployed by [create_faucet]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TokenConfig {
+    pub symbol: String,
+    #[serde(default = "default_decimals")]
+    pub decimals: u8,
+    #[serde(default = "default_max_supply")]
+    pub max_supply: u64,
+}
+
+fn default_decimals() -> u8 {
+    8
+}
+
+fn default_max_supply() -> u64 {
+    1_000_000_000
+}
+
+/// Traders created by a scenario, named `<name_prefix>-<i>` and funded with `balances`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TradersConfig {
+    pub count: usize,
+    #[serde(default = "default_name_prefix")]
+    pub name_prefix: String,
+    /// Amount minted to every trader, keyed by token symbol
+    #[serde(default)]
+    pub balances: BTreeMap<String, u64>,
+}
+
+fn default_name_prefix() -> String {
+    "trader".to_string()
+}
+
+/// Local development scenario, see `scenarios/two_tokens.yaml`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Scenario {
+    pub tokens: Vec<TokenConfig>,
+    pub traders: TradersConfig,
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let file = std::fs::File::open(path)?;
+        let scenario: Scenario = serde_yaml::from_reader(file)?;
+
+        // Fail before deploying anything if a balance refers to a token that is not deployed
+        for symbol in scenario.traders.balances.keys() {
+            if !scenario.tokens.iter().any(|token| &token.symbol == symbol) {
+                return Err(ConfigError::UnknownToken(symbol.clone()));
+            }
+        }
+
+        Ok(scenario)
+    }
+}
+
+/// Name to account id mapping of the dev accounts, stored as yaml
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct DevAccounts(BTreeMap<String, String>);
+
+impl DevAccounts {
+    /// Loads the mapping, an absent file is an empty mapping
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        match std::fs::File::open(path) {
+            Ok(file) => Ok(serde_yaml::from_reader(file)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+        let file = std::fs::File::create(path)?;
+        serde_yaml::to_writer(file, self)?;
+        Ok(())
+    }
+
+    pub fn insert(&mut self, name: &str, account_id: AccountId) {
+        self.0.insert(name.to_string(), account_id.to_hex());
+    }
+
+    /// Resolves a name to its account id, hex account ids are accepted as is
+    pub fn resolve(&self, name_or_id: &str) -> Result<AccountId, ConfigError> {
+        let account_id = self.0.get(name_or_id).map_or(name_or_id, String::as_str);
+
+        AccountId::from_hex(account_id).map_err(|_| ConfigError::UnknownAccount(name_or_id.into()))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.0.iter()
+    }
+}
+
+/// Creates a fungible faucet authenticated with RpoFalcon512
+/// The faucet is added to the client and its secret key to the keystore
+pub async fn create_faucet(
+    client: &mut Client,
+    keystore: &FilesystemKeyStore<StdRng>,
+    token: &TokenConfig,
+) -> Result<Account, DevError> {
+    // Faucet seed
+    let mut init_seed = [0u8; 32];
+    client.rng().fill_bytes(&mut init_seed);
+
+    // Faucet parameters
+    let symbol = TokenSymbol::new(&token.symbol)?;
+    let max_supply = Felt::new(token.max_supply);
+
+    // Generate key pair
+    let key_pair = SecretKey::with_rng(client.rng());
+
+    let anchor_block = client.get_latest_epoch_block().await?;
+
+    // Build the account
+    let builder = AccountBuilder::new(init_seed)
+        .anchor((&anchor_block).try_into()?)
+        .account_type(AccountType::FungibleFaucet)
+        .storage_mode(AccountStorageMode::Public)
+        .with_component(RpoFalcon512::new(key_pair.public_key()))
+        .with_component(BasicFungibleFaucet::new(
+            symbol,
+            token.decimals,
+            max_supply,
+        )?);
+
+    let (faucet_account, seed) = builder.build()?;
+
+    // Add the faucet to the client
+    client
+        .add_account(&faucet_account, Some(seed), false)
+        .await?;
+
+    // Add the key pair to the keystore
+    keystore.add_key(&AuthSecretKey::RpoFalcon512(key_pair))?;
+
+    // Resync to show newly deployed faucet
+    client.sync_state().await?;
+    Ok(faucet_account)
+}
+
+/// Mints `amount` of the faucet token to `target` and consumes the minted note with it
+///
+/// Both the faucet and the target account must be tracked by the client with their keys in the
+/// keystore.
+pub async fn mint_and_consume(
+    client: &mut Client,
+    faucet_id: AccountId,
+    target_id: AccountId,
+    amount: u64,
+) -> Result<(), DevError> {
+    let fungible_asset = FungibleAsset::new(faucet_id, amount)?;
+
+    let transaction_request = TransactionRequestBuilder::mint_fungible_asset(
+        fungible_asset,
+        target_id,
+        NoteType::Public,
+        client.rng(),
+    )?
+    .build()?;
+
+    let tx_execution_result = client
+        .new_transaction(faucet_id, transaction_request)
+        .await?;
+    let minted_note_id = tx_execution_result
+        .created_notes()
+        .iter()
+        .next()
+        .ok_or(DevError::MissingMintNote)?
+        .id();
+    client.submit_transaction(tx_execution_result).await?;
+
+    wait_for_consumable(client, target_id, minted_note_id).await?;
+
+    let transaction_request =
+        TransactionRequestBuilder::consume_notes(vec![minted_note_id]).build()?;
+    let tx_execution_result = client
+        .new_transaction(target_id, transaction_request)
+        .await?;
+    client.submit_transaction(tx_execution_result).await?;
+
+    client.sync_state().await?;
+    Ok(())
+}
+
+/// Waits for `note_id` to be committed and consumable by `account_id`, gives up after
+/// [MINT_WAIT_SECS]
+async fn wait_for_consumable(
+    client: &mut Client,
+    account_id: AccountId,
+    note_id: NoteId,
+) -> Result<(), DevError> {
+    for _ in 0..MINT_WAIT_SECS / MINT_POLL_SECS {
+        client.sync_state().await?;
+
+        let consumable_notes = client.get_consumable_notes(Some(account_id)).await?;
+        if consumable_notes
+            .iter()
+            .any(|(note, _)| note.id() == note_id)
+        {
+            return Ok(());
+        }
+
+        tokio::time::sleep(Duration::from_secs(MINT_POLL_SECS)).await;
+    }
+
+    Err(DevError::MintNoteNotCommitted(note_id))
+}
+
+/// Deploys the scenario tokens, creates the traders and funds them
+///
+/// Every faucet is named after its symbol and every trader `<name_prefix>-<i>` in `accounts`.
+pub async fn seed_scenario(
+    client: &mut Client,
+    keystore: &FilesystemKeyStore<StdRng>,
+    scenario: &Scenario,
+    accounts: &mut DevAccounts,
+) -> Result<(), DevError> {
+    let mut faucets = BTreeMap::new();
+    for token in &scenario.tokens {
+        let faucet = create_faucet(client, keystore, token).await?;
+        accounts.insert(&token.symbol, faucet.id());
+        faucets.insert(token.symbol.clone(), faucet.id());
+    }
+
+    for i in 0..scenario.traders.count {
+        let name = format!("{}-{}", scenario.traders.name_prefix, i);
+        let trader = create_wallet(client, keystore, AccountStorageMode::Public).await?;
+        accounts.insert(&name, trader.id());
+        client.sync_state().await?;
+
+        for (symbol, amount) in &scenario.traders.balances {
+            let faucet_id = faucets
+                .get(symbol)
+                .ok_or_else(|| ConfigError::UnknownToken(symbol.clone()))?;
+            mint_and_consume(client, *faucet_id, trader.id(), *amount).await?;
+        }
+    }
+
+    Ok(())
+}
@@ -1 +1,7 @@
+pub use miden_dark_pool_core::swap_note;
+
+pub mod attestation;
 pub mod common;
+pub mod dev;
+pub mod protocol;
+pub mod transport;
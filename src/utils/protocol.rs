@@ -0,0 +1,122 @@
+use crate::matching::events::MarketEvent;
+use crate::matching::market::MarketSummary;
+use crate::utils::attestation::AttestationReport;
+use crate::utils::common::MidenNote;
+use crate::utils::transport::SealedNote;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+pub const MATCHER_ADDRESS: &str = "127.0.0.1:8080";
+
+#[derive(Error, Debug)]
+pub enum ProtocolError {
+    #[error("connection error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("malformed message: {0}")]
+    Encoding(#[from] bincode::Error),
+
+    #[error("unexpected response from the matcher")]
+    UnexpectedResponse,
+}
+
+// Messages sent by the user to the matcher
+#[derive(Serialize, Deserialize, Debug)]
+pub enum MatcherRequest {
+    /// New SWAPp order, transport_key is the x25519 key the resulting notes are sealed to
+    SubmitOrder {
+        note: MidenNote,
+        transport_key: [u8; 32],
+    },
+
+    /// First request of the attestation handshake, answered with the attestation report of the
+    /// matcher for `nonce`. The connection stays open for the next request.
+    ///
+    /// Attestation is advisory: the report is verified by the client, and the matcher still
+    /// accepts unattested [MatcherRequest::SubmitOrder] requests from clients that skip it.
+    Attest { nonce: [u8; 32] },
+
+    /// New SWAPp order sealed to the attested transport key of the matcher, see
+    /// [MatcherRequest::SubmitOrder]
+    SubmitSealedOrder {
+        sealed_note: SealedNote,
+        transport_key: [u8; 32],
+    },
+
+    /// Drains the mailbox of the given account (hex id), answered with
+    /// [MatcherResponse::KeyChallenge], or with no notes if the account has no transport key
+    FetchNotes { account_id: String },
+
+    /// Opened secret of a [MatcherResponse::KeyChallenge], answered with the notes of the
+    /// account if it matches
+    AnswerChallenge { secret: Vec<u8> },
+
+    /// Sent after [MatcherResponse::Notes] on the connection that answered the key challenge,
+    /// removes the notes the client imported (by the ephemeral key they are sealed with) from
+    /// the mailbox. The other notes are handed out again by the next fetch.
+    AckNotes { ephemeral_keys: Vec<[u8; 32]> },
+
+    /// Depth, midpoint and last trade of the pair of two faucets (hex ids, in any order),
+    /// depth prices are rounded to multiples of `tick` or of the matcher depth tick, the coarser
+    QueryMarket {
+        faucet_a: String,
+        faucet_b: String,
+        tick: Option<f64>,
+    },
+
+    /// Keeps the connection open and streams the market events of the matcher
+    Subscribe,
+}
+
+// Messages sent by the matcher in reply to a request
+#[derive(Serialize, Deserialize, Debug)]
+pub enum MatcherResponse {
+    /// Serialized `NoteFile`s sealed to the transport key of the account
+    Notes(Vec<SealedNote>),
+
+    /// Random secret sealed to the transport key of the account fetching its notes, the notes
+    /// are only sent once the secret is returned with [MatcherRequest::AnswerChallenge]
+    KeyChallenge(SealedNote),
+
+    /// Reply to [MatcherRequest::Attest]
+    Attestation(AttestationReport),
+
+    /// Reply to [MatcherRequest::QueryMarket]
+    Market(MarketSummary),
+
+    /// Sent to subscribers, starting with a depth snapshot of every pair in the book
+    Event(MarketEvent),
+}
+
+/// Writes a length prefixed (u32 big endian) bincode message
+pub async fn write_message<W, T>(stream: &mut W, message: &T) -> Result<(), ProtocolError>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    let encoded = bincode::serialize(message)?;
+    let length = (encoded.len() as u32).to_be_bytes();
+
+    stream.write_all(&length).await?;
+    stream.write_all(&encoded).await?;
+    Ok(())
+}
+
+/// Reads a message written by [write_message]
+pub async fn read_message<R, T>(stream: &mut R) -> Result<T, ProtocolError>
+where
+    R: AsyncRead + Unpin,
+    T: DeserializeOwned,
+{
+    let mut len_buf = [0u8; 4];
+    stream.read_exact(&mut len_buf).await?;
+
+    let len = u32::from_be_bytes(len_buf) as usize;
+    let mut buffer = vec![0u8; len];
+    stream.read_exact(&mut buffer).await?;
+
+    Ok(bincode::deserialize(&buffer)?)
+}
@@ -0,0 +1,139 @@
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use miden_client::account::AccountId;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+/// Directory holding the transport keys of the accounts managed by the user CLI
+pub const TRANSPORT_KEYS_DIR: &str = "./transport_keys";
+
+#[derive(Error, Debug)]
+pub enum TransportError {
+    #[error("transport key io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("transport key file {0} is malformed")]
+    MalformedKey(PathBuf),
+
+    #[error("failed to decrypt sealed note")]
+    Decryption,
+}
+
+// A note encrypted to the transport key of its recipient
+// ephemeral_key is the sender's one time x25519 public key
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SealedNote {
+    pub ephemeral_key: [u8; 32],
+    pub nonce: [u8; 12],
+    pub ciphertext: Vec<u8>,
+}
+
+/// x25519 key used to receive notes from the matcher out of band
+///
+/// Miden accounts only hold signature keys, so every account gets a separate encryption key
+/// stored next to the client keystore and announced to the matcher with each order.
+pub struct TransportKey {
+    secret: StaticSecret,
+}
+
+impl TransportKey {
+    /// Loads the transport key of `account_id` from `dir`, generating it on first use
+    ///
+    /// A generated key is only readable by its owner on unix.
+    pub fn load_or_create(
+        dir: impl AsRef<Path>,
+        account_id: AccountId,
+    ) -> Result<Self, TransportError> {
+        let path = dir.as_ref().join(format!("{}.key", account_id.to_hex()));
+
+        if path.exists() {
+            let bytes: [u8; 32] = std::fs::read(&path)?
+                .try_into()
+                .map_err(|_| TransportError::MalformedKey(path.clone()))?;
+            return Ok(Self {
+                secret: StaticSecret::from(bytes),
+            });
+        }
+
+        let bytes: [u8; 32] = rand::rng().random();
+        std::fs::create_dir_all(dir)?;
+
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options.open(&path)?.write_all(&bytes)?;
+
+        Ok(Self {
+            secret: StaticSecret::from(bytes),
+        })
+    }
+
+    /// Generates a key kept in memory only, e.g. the transport key of the matcher
+    pub fn generate() -> Self {
+        Self {
+            secret: StaticSecret::from(rand::rng().random::<[u8; 32]>()),
+        }
+    }
+
+    pub fn public_key(&self) -> [u8; 32] {
+        PublicKey::from(&self.secret).to_bytes()
+    }
+
+    /// Decrypts a note sealed with [seal] to this key
+    pub fn open(&self, sealed: &SealedNote) -> Result<Vec<u8>, TransportError> {
+        let ephemeral_key = PublicKey::from(sealed.ephemeral_key);
+        let shared_secret = self.secret.diffie_hellman(&ephemeral_key);
+        let cipher = cipher(
+            shared_secret.as_bytes(),
+            &sealed.ephemeral_key,
+            &self.public_key(),
+        );
+
+        cipher
+            .decrypt(Nonce::from_slice(&sealed.nonce), sealed.ciphertext.as_ref())
+            .map_err(|_| TransportError::Decryption)
+    }
+}
+
+/// Encrypts `payload` to the transport key `recipient`
+/// ECDH with a fresh ephemeral key, the shared secret is hashed into a ChaCha20Poly1305 key
+pub fn seal(recipient: [u8; 32], payload: &[u8]) -> SealedNote {
+    let mut rng = rand::rng();
+
+    let ephemeral_secret = StaticSecret::from(rng.random::<[u8; 32]>());
+    let ephemeral_key = PublicKey::from(&ephemeral_secret).to_bytes();
+    let shared_secret = ephemeral_secret.diffie_hellman(&PublicKey::from(recipient));
+    let cipher = cipher(shared_secret.as_bytes(), &ephemeral_key, &recipient);
+
+    let nonce: [u8; 12] = rng.random();
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), payload)
+        .expect("encryption of an in-memory buffer cannot fail");
+
+    SealedNote {
+        ephemeral_key,
+        nonce,
+        ciphertext,
+    }
+}
+
+/// key = sha256(shared_secret || ephemeral_key || recipient_key)
+fn cipher(
+    shared_secret: &[u8; 32],
+    ephemeral_key: &[u8; 32],
+    recipient: &[u8; 32],
+) -> ChaCha20Poly1305 {
+    let mut hasher = sha2::Sha256::new();
+    hasher.update(shared_secret);
+    hasher.update(ephemeral_key);
+    hasher.update(recipient);
+    let key: [u8; 32] = hasher.finalize().into();
+
+    ChaCha20Poly1305::new(Key::from_slice(&key))
+}
@@ -0,0 +1,113 @@
+use miden_dark_pool::utils::attestation::{
+    AttestationError, Attestor, MockProvider, Verifier, attest_matcher,
+};
+use miden_dark_pool::utils::protocol::{
+    MatcherRequest, MatcherResponse, read_message, write_message,
+};
+use miden_dark_pool::utils::transport::{TransportKey, seal};
+use miden_objects::utils::bytes_to_hex_string;
+
+const MEASUREMENT: [u8; 32] = [1; 32];
+const NONCE: [u8; 32] = [9; 32];
+
+fn attestor() -> Attestor<MockProvider> {
+    Attestor::new(TransportKey::generate(), MockProvider::new(MEASUREMENT))
+}
+
+#[test]
+fn test_verify_attestation_report() {
+    let attestor = attestor();
+    let report = attestor.report(NONCE);
+    let verifier = Verifier::new(vec![[2; 32], MEASUREMENT]).allow_mock(true);
+
+    assert_eq!(
+        verifier.verify(&report, NONCE).unwrap(),
+        attestor.transport_key().public_key()
+    );
+
+    // hex measurements as passed to the user CLI
+    let verifier = Verifier::from_hex(&[bytes_to_hex_string(MEASUREMENT)])
+        .unwrap()
+        .allow_mock(true);
+    assert!(verifier.verify(&report, NONCE).is_ok());
+    assert!(matches!(
+        Verifier::from_hex(&["0x12"]),
+        Err(AttestationError::InvalidMeasurement(_))
+    ));
+}
+
+#[test]
+fn test_reject_attestation_report() {
+    let attestor = attestor();
+    let report = attestor.report(NONCE);
+    let verifier = Verifier::new(vec![MEASUREMENT]).allow_mock(true);
+
+    assert!(matches!(
+        Verifier::new(vec![MEASUREMENT]).verify(&report, NONCE),
+        Err(AttestationError::MockNotAllowed)
+    ));
+    assert!(matches!(
+        Verifier::new(vec![[2; 32]])
+            .allow_mock(true)
+            .verify(&report, NONCE),
+        Err(AttestationError::UnexpectedMeasurement(_))
+    ));
+
+    // replayed report
+    assert!(matches!(
+        verifier.verify(&report, [8; 32]),
+        Err(AttestationError::KeyNotBound)
+    ));
+
+    // transport key swapped by a man in the middle
+    let mut swapped = report.clone();
+    swapped.transport_key = TransportKey::generate().public_key();
+    assert!(matches!(
+        verifier.verify(&swapped, NONCE),
+        Err(AttestationError::KeyNotBound)
+    ));
+
+    // measurement changed without signing it
+    let mut forged = report.clone();
+    forged.measurement = [2; 32];
+    assert!(matches!(
+        Verifier::new(vec![[2; 32]])
+            .allow_mock(true)
+            .verify(&forged, NONCE),
+        Err(AttestationError::InvalidSignature)
+    ));
+}
+
+#[tokio::test]
+async fn test_attestation_handshake() {
+    let attestor = attestor();
+    let matcher_key = attestor.transport_key().public_key();
+    let (mut user, mut matcher) = tokio::io::duplex(4096);
+
+    let server = tokio::spawn(async move {
+        let MatcherRequest::Attest { nonce } = read_message(&mut matcher).await.unwrap() else {
+            panic!("expected an attestation request");
+        };
+        let response = MatcherResponse::Attestation(attestor.report(nonce));
+        write_message(&mut matcher, &response).await.unwrap();
+
+        let MatcherRequest::SubmitSealedOrder { sealed_note, .. } =
+            read_message(&mut matcher).await.unwrap()
+        else {
+            panic!("expected a sealed order");
+        };
+        attestor.transport_key().open(&sealed_note).unwrap()
+    });
+
+    let verifier = Verifier::new(vec![MEASUREMENT]).allow_mock(true);
+    let attested_key = attest_matcher(&mut user, &verifier).await.unwrap();
+    assert_eq!(attested_key, matcher_key);
+
+    // the order is only readable by the attested matcher
+    let request = MatcherRequest::SubmitSealedOrder {
+        sealed_note: seal(attested_key, b"order"),
+        transport_key: [7; 32],
+    };
+    write_message(&mut user, &request).await.unwrap();
+    assert_eq!(server.await.unwrap(), b"order");
+}
@@ -0,0 +1,29 @@
+use miden_dark_pool::utils::dev::{ConfigError, DevAccounts, Scenario};
+use miden_objects::account::AccountId;
+use miden_objects::testing::account_id::ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_IMMUTABLE_CODE;
+use std::path::Path;
+
+#[test]
+fn test_load_two_tokens_scenario() {
+    let scenario = Scenario::load(Path::new("scenarios/two_tokens.yaml")).unwrap();
+
+    assert_eq!(scenario.tokens.len(), 2);
+    assert_eq!(scenario.traders.count, 4);
+    assert_eq!(scenario.traders.balances["ETH"], 1000);
+    assert_eq!(scenario.traders.balances["BTC"], 100);
+}
+
+#[test]
+fn test_dev_accounts_resolve() {
+    let account_id = AccountId::try_from(ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_IMMUTABLE_CODE).unwrap();
+
+    let mut accounts = DevAccounts::default();
+    accounts.insert("alice", account_id);
+
+    assert_eq!(accounts.resolve("alice").unwrap(), account_id);
+    assert_eq!(accounts.resolve(&account_id.to_hex()).unwrap(), account_id);
+    assert!(matches!(
+        accounts.resolve("bob"),
+        Err(ConfigError::UnknownAccount(_))
+    ));
+}
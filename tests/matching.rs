@@ -0,0 +1,202 @@
+use miden_dark_pool::matching::book::compute_fills;
+use miden_dark_pool::matching::events::{EventBus, MarketEvent, size_bucket};
+use miden_dark_pool::matching::mailbox::Mailbox;
+use miden_dark_pool::utils::swap_note::Side;
+use miden_dark_pool::utils::transport::TransportKey;
+use miden_dark_pool_core::testing::{account, order};
+use miden_objects::block::BlockNumber;
+use miden_objects::note::NoteFile;
+use miden_objects::testing::account_id::{
+    ACCOUNT_ID_PRIVATE_SENDER, ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET,
+    ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1, ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE,
+};
+use miden_objects::utils::Deserializable;
+
+#[test]
+fn test_mailbox_seals_to_transport_key() {
+    let recipient = account(ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE);
+    let key_dir = std::env::temp_dir().join("miden_dark_pool_transport_keys");
+    let _ = std::fs::remove_dir_all(&key_dir);
+    let transport_key = TransportKey::load_or_create(&key_dir, recipient).unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let key_file = key_dir.join(format!("{}.key", recipient.to_hex()));
+        let mode = std::fs::metadata(key_file).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    let note = order(
+        ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE,
+        (ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET, 10),
+        (ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1, 10),
+        1,
+    )
+    .note;
+    let note_file = NoteFile::NoteDetails {
+        details: note.clone().into(),
+        after_block_num: BlockNumber::from(7),
+        tag: Some(note.metadata().tag()),
+    };
+
+    let mut mailbox = Mailbox::new();
+    let stranger = account(ACCOUNT_ID_PRIVATE_SENDER);
+    assert!(!mailbox.deliver(stranger, &note_file));
+
+    mailbox.propose(note.id(), recipient, transport_key.public_key());
+    assert!(mailbox.confirm(note.id()));
+    assert!(mailbox.deliver(recipient, &note_file));
+
+    // a key announced later by anyone else does not replace the registered one
+    let other_key = TransportKey::generate();
+    let other_note = order(
+        ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE,
+        (ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET, 10),
+        (ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1, 10),
+        2,
+    )
+    .note;
+    mailbox.propose(other_note.id(), recipient, other_key.public_key());
+    assert!(!mailbox.confirm(other_note.id()));
+
+    // the notes are only handed out to the holder of the registered key
+    let (challenge, sealed_secret) = mailbox.challenge(recipient).unwrap();
+    assert!(other_key.open(&sealed_secret).is_err());
+    assert!(mailbox.peek(&challenge, &[0; 32]).is_none());
+
+    let secret = transport_key.open(&sealed_secret).unwrap();
+    let sealed_notes = mailbox.peek(&challenge, &secret).unwrap();
+    assert_eq!(sealed_notes.len(), 1);
+    assert!(mailbox.challenge(stranger).is_none());
+
+    let payload = transport_key.open(&sealed_notes[0]).unwrap();
+    let NoteFile::NoteDetails { details, .. } = NoteFile::read_from_bytes(&payload).unwrap() else {
+        panic!("expected note details");
+    };
+    assert_eq!(details.id(), note.id());
+}
+
+#[test]
+fn test_mailbox_keeps_notes_until_acknowledged() {
+    let recipient = account(ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE);
+    let transport_key = TransportKey::generate();
+    let note = order(
+        ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE,
+        (ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET, 10),
+        (ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1, 10),
+        1,
+    )
+    .note;
+    let note_file = NoteFile::NoteDetails {
+        details: note.clone().into(),
+        after_block_num: BlockNumber::from(7),
+        tag: Some(note.metadata().tag()),
+    };
+
+    let mut mailbox = Mailbox::new();
+    mailbox.propose(note.id(), recipient, transport_key.public_key());
+    assert!(mailbox.confirm(note.id()));
+    assert!(mailbox.deliver(recipient, &note_file));
+    assert!(mailbox.deliver(recipient, &note_file));
+
+    let (challenge, sealed_secret) = mailbox.challenge(recipient).unwrap();
+    let secret = transport_key.open(&sealed_secret).unwrap();
+    let sealed_notes = mailbox.peek(&challenge, &secret).unwrap();
+    assert_eq!(sealed_notes.len(), 2);
+
+    // the acknowledgement needs the answered challenge
+    let imported = [sealed_notes[0].ephemeral_key];
+    assert!(!mailbox.acknowledge(&challenge, &[0; 32], &imported));
+    assert_eq!(mailbox.peek(&challenge, &secret).unwrap().len(), 2);
+
+    // the import of the second note failed, it is handed out again
+    assert!(mailbox.acknowledge(&challenge, &secret, &imported));
+    let remaining = mailbox.peek(&challenge, &secret).unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].ephemeral_key, sealed_notes[1].ephemeral_key);
+
+    assert!(mailbox.acknowledge(&challenge, &secret, &[remaining[0].ephemeral_key]));
+    assert!(mailbox.peek(&challenge, &secret).unwrap().is_empty());
+}
+
+#[test]
+fn test_mailbox_unconfirmed_key_does_not_hijack_delivery() {
+    let victim = account(ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE);
+    let victim_key = TransportKey::generate();
+    let attacker_key = TransportKey::generate();
+
+    let order_note = |serial: u64| {
+        order(
+            ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE,
+            (ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET, 10),
+            (ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1, 10),
+            serial,
+        )
+        .note
+    };
+    // the forged order claiming the victim as creator is submitted first
+    let forged = order_note(1);
+    let genuine = order_note(2);
+    let mut mailbox = Mailbox::new();
+    mailbox.propose(forged.id(), victim, attacker_key.public_key());
+    mailbox.propose(genuine.id(), victim, victim_key.public_key());
+
+    // notes are held until a key is confirmed
+    let note_file = NoteFile::NoteDetails {
+        details: genuine.clone().into(),
+        after_block_num: BlockNumber::from(7),
+        tag: Some(genuine.metadata().tag()),
+    };
+    assert!(mailbox.deliver(victim, &note_file));
+    assert!(mailbox.challenge(victim).is_none());
+
+    // only the genuine order is found on chain, the forged one is dropped with its order
+    assert!(mailbox.confirm(genuine.id()));
+    mailbox.withdraw(forged.id());
+    assert!(!mailbox.confirm(forged.id()));
+    assert!(mailbox.deliver(victim, &note_file));
+
+    let (challenge, sealed_secret) = mailbox.challenge(victim).unwrap();
+    assert!(attacker_key.open(&sealed_secret).is_err());
+    let secret = victim_key.open(&sealed_secret).unwrap();
+    let sealed_notes = mailbox.peek(&challenge, &secret).unwrap();
+    assert_eq!(sealed_notes.len(), 2);
+    for sealed in &sealed_notes {
+        assert!(attacker_key.open(sealed).is_err());
+        assert!(victim_key.open(sealed).is_ok());
+    }
+}
+
+#[tokio::test]
+async fn test_fill_event_published_to_subscribers() {
+    let eth = ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET;
+    let btc = ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1;
+
+    let maker = order(ACCOUNT_ID_PRIVATE_SENDER, (eth, 100), (btc, 40), 1);
+    let taker = order(
+        ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE,
+        (btc, 30),
+        (eth, 45),
+        2,
+    );
+    let matched = compute_fills(&maker, &taker).unwrap();
+
+    let events = EventBus::new();
+    let mut receiver = events.subscribe();
+    events.publish(MarketEvent::fill_executed(&matched));
+
+    // 30 BTC against 75 ETH, sized and priced in the base asset of the pair
+    let pair = maker.pair();
+    let (size, price) = match maker.side() {
+        Side::Sell => (75, 30.0 / 75.0),
+        Side::Buy => (30, 75.0 / 30.0),
+    };
+    assert_eq!(
+        receiver.recv().await.unwrap(),
+        MarketEvent::FillExecuted {
+            pair: pair.into(),
+            size_bucket: size_bucket(size),
+            price,
+        }
+    );
+}
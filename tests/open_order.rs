@@ -0,0 +1,52 @@
+use miden_dark_pool::cli::open_order::{PreflightError, preflight_check};
+use miden_dark_pool_core::testing::account;
+use miden_objects::asset::{AssetVault, FungibleAsset};
+use miden_objects::testing::account_id::{
+    ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET, ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1,
+    ACCOUNT_ID_PUBLIC_NON_FUNGIBLE_FAUCET,
+};
+
+#[test]
+fn test_preflight_check() {
+    let eth = account(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET);
+    let btc = account(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1);
+    let nft = account(ACCOUNT_ID_PUBLIC_NON_FUNGIBLE_FAUCET);
+    let vault = AssetVault::new(&[FungibleAsset::new(eth, 100).unwrap().into()]).unwrap();
+
+    let (offered, requested) = preflight_check(&vault, eth, 100, btc, 50).unwrap();
+    assert_eq!(offered, FungibleAsset::new(eth, 100).unwrap());
+    assert_eq!(requested, FungibleAsset::new(btc, 50).unwrap());
+
+    assert_eq!(
+        preflight_check(&vault, eth, 101, btc, 50),
+        Err(PreflightError::InsufficientBalance {
+            faucet_id: eth,
+            available: 100,
+            required: 101
+        })
+    );
+    assert_eq!(
+        preflight_check(&vault, btc, 1, eth, 50),
+        Err(PreflightError::InsufficientBalance {
+            faucet_id: btc,
+            available: 0,
+            required: 1
+        })
+    );
+    assert_eq!(
+        preflight_check(&vault, eth, 100, nft, 1),
+        Err(PreflightError::NotFungibleFaucet(nft))
+    );
+    assert_eq!(
+        preflight_check(&vault, eth, 100, eth, 1),
+        Err(PreflightError::SameAsset(eth))
+    );
+    assert_eq!(
+        preflight_check(&vault, eth, 100, btc, FungibleAsset::MAX_AMOUNT + 1),
+        Err(PreflightError::InvalidAmount(FungibleAsset::MAX_AMOUNT + 1))
+    );
+    assert_eq!(
+        preflight_check(&vault, eth, 0, btc, 50),
+        Err(PreflightError::InvalidAmount(0))
+    );
+}
@@ -0,0 +1,1266 @@
+use std::collections::BTreeMap;
+
+use miden_dark_pool::cli::open_order::get_serial_num;
+use miden_dark_pool::matching::book::{
+    RestingOrder, compute_fills, compute_multi_fills, compute_pegged_fills,
+};
+use miden_dark_pool::matching::ring::compute_ring_fills;
+use miden_dark_pool::utils::common::{
+    OrderFee, OrderTerms, SwapNoteError, TimeInForce, calculate_fee, calculate_tokens_a_for_b,
+    create_swap_note_successor, reconstruct_fee_note, reconstruct_p2id_note,
+};
+use miden_dark_pool::utils::swap_note::{
+    PRIVATE_SWAPP_SCRIPT_ROOT, PUBLIC_SWAPP_SCRIPT_ROOT, SWAPP_NUM_INPUTS, ScriptProfile, Side,
+    SwapNoteBuilder, SwapNoteInputs, SwapOrder, TradingPair, compile_swap_note_script,
+    swap_note_script,
+};
+use miden_lib::transaction::TransactionKernel;
+use miden_objects::Felt;
+use miden_objects::account::AccountId;
+use miden_objects::asset::{Asset, FungibleAsset};
+use miden_objects::block::BlockNumber;
+use miden_objects::note::{
+    Note, NoteExecutionHint, NoteExecutionMode, NoteId, NoteInputs, NoteMetadata, NoteRecipient,
+    NoteScript, NoteTag, NoteType,
+};
+use miden_objects::testing::account_id::{
+    ACCOUNT_ID_PRIVATE_SENDER, ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET,
+    ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1, ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE,
+};
+use miden_objects::transaction::OutputNote;
+use miden_tx::testing::Auth;
+
+pub mod utils;
+use utils::test_utils::{mock_chain, set_fill_amount, set_fills, set_note_args};
+
+#[tokio::test]
+async fn test_reconstruct_p2id_note() {
+    let (mut chain, faucet_a, faucet_b) = mock_chain();
+    let creator = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let consumer = chain.add_existing_wallet(Auth::BasicAuth, vec![faucet_b.mint(100)]);
+
+    let offered_asset: Asset = faucet_a.mint(100);
+    let requested_asset: Asset = faucet_b.mint(50);
+    let serial_num = get_serial_num(creator.id());
+
+    let swap_note = SwapNoteBuilder::new(creator.id(), offered_asset, requested_asset, serial_num)
+        .build()
+        .unwrap();
+    chain.add_pending_note(swap_note.clone());
+    chain.seal_next_block();
+
+    // partially fill the order: 20 out of the 50 requested tokens
+    let filled_amount = 20;
+    let mut tx_context = chain
+        .build_tx_context(consumer.id(), &[swap_note.id()], &[])
+        .build();
+    set_fill_amount(&mut tx_context, swap_note.id(), filled_amount);
+    let executed_tx = tx_context.execute().await.unwrap();
+
+    let (p2id_details, p2id_tag) = reconstruct_p2id_note(&swap_note, 0, filled_amount).unwrap();
+
+    let p2id_note = executed_tx.output_notes().get_note(0);
+    assert_eq!(p2id_note.id(), p2id_details.id());
+    assert_eq!(p2id_note.metadata().tag(), p2id_tag);
+}
+
+#[tokio::test]
+async fn test_settle_crossing_orders() {
+    let (mut chain, faucet_a, faucet_b) = mock_chain();
+    let alice = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let bob = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let executor = chain.add_existing_wallet(Auth::BasicAuth, vec![faucet_b.mint(50)]);
+
+    // alice sells 100 ETH for 50 BTC, bob sells 60 BTC for 110 ETH
+    let maker_note = SwapNoteBuilder::new(
+        alice.id(),
+        faucet_a.mint(100),
+        faucet_b.mint(50),
+        get_serial_num(alice.id()),
+    )
+    .build()
+    .unwrap();
+    let taker_note = SwapNoteBuilder::new(
+        bob.id(),
+        faucet_b.mint(60),
+        faucet_a.mint(110),
+        get_serial_num(bob.id()),
+    )
+    .build()
+    .unwrap();
+    chain.add_pending_note(maker_note.clone());
+    chain.add_pending_note(taker_note.clone());
+    chain.seal_next_block();
+
+    let maker = RestingOrder::from_note(maker_note.clone()).unwrap();
+    let taker = RestingOrder::from_note(taker_note.clone()).unwrap();
+    let matched = compute_fills(&maker, &taker).unwrap();
+    assert!(matched.maker.is_complete());
+    assert!(!matched.taker.is_complete());
+
+    let mut expected_ids = Vec::new();
+    for fill in [&matched.maker, &matched.taker] {
+        let (p2id_details, _) =
+            reconstruct_p2id_note(&fill.order.note, 0, fill.filled_amount).unwrap();
+        expected_ids.push(p2id_details.id());
+
+        let successor = create_swap_note_successor(
+            &fill.order.note,
+            executor.id(),
+            fill.filled_amount,
+            fill.amount_out,
+        )
+        .unwrap();
+        expected_ids.extend(successor.map(|note| note.id()));
+    }
+
+    let mut tx_context = chain
+        .build_tx_context(executor.id(), &[maker_note.id(), taker_note.id()], &[])
+        .build();
+    set_fills(&mut tx_context, [&matched.maker, &matched.taker]);
+    let executed_tx = tx_context.execute().await.unwrap();
+
+    let output_ids: Vec<_> = executed_tx
+        .output_notes()
+        .iter()
+        .map(|note| note.id())
+        .collect();
+    assert_eq!(output_ids, expected_ids);
+
+    // the executor keeps the price improvement
+    let vault_delta = executed_tx.account_delta().vault().fungible();
+    let executor_gain = matched.taker.amount_out - matched.maker.filled_amount;
+    let gain_b = vault_delta
+        .iter()
+        .find(|(faucet_id, _)| **faucet_id == faucet_b.id())
+        .map(|(_, amount)| *amount);
+    assert_eq!(gain_b, Some(executor_gain as i64));
+}
+
+#[tokio::test]
+async fn test_settle_pegged_orders_at_reference_price() {
+    let (mut chain, faucet_a, faucet_b) = mock_chain();
+    let alice = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let bob = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let executor = chain.add_existing_wallet(
+        Auth::BasicAuth,
+        vec![faucet_a.mint(2000), faucet_b.mint(2000)],
+    );
+
+    // alice sells 1000 base for at least 2 quote each, pegged, bob buys 600 base for at most 3
+    let pair = TradingPair::new(faucet_a.id(), faucet_b.id());
+    let asset = |faucet_id: AccountId, amount: u64| -> Asset {
+        FungibleAsset::new(faucet_id, amount).unwrap().into()
+    };
+    let maker_note = SwapNoteBuilder::new(
+        alice.id(),
+        asset(pair.base, 1000),
+        asset(pair.quote, 2000),
+        get_serial_num(alice.id()),
+    )
+    .terms(OrderTerms {
+        pegged: true,
+        ..Default::default()
+    })
+    .build()
+    .unwrap();
+    let taker_note = SwapNoteBuilder::new(
+        bob.id(),
+        asset(pair.quote, 1800),
+        asset(pair.base, 600),
+        get_serial_num(bob.id()),
+    )
+    .build()
+    .unwrap();
+    chain.add_pending_note(maker_note.clone());
+    chain.add_pending_note(taker_note.clone());
+    chain.seal_next_block();
+
+    let maker = RestingOrder::from_note(maker_note.clone()).unwrap();
+    let taker = RestingOrder::from_note(taker_note.clone()).unwrap();
+    let matched = compute_pegged_fills(&maker, &taker, 2.5).unwrap();
+    assert!(matched.maker.improvement > 0 && matched.taker.improvement > 0);
+
+    let mut expected_ids = Vec::new();
+    for fill in [&matched.maker, &matched.taker] {
+        // the creator is paid the filled amount and the improvement
+        let (p2id_details, _) = reconstruct_p2id_note(&fill.order.note, 0, fill.paid()).unwrap();
+        expected_ids.push(p2id_details.id());
+
+        let successor = create_swap_note_successor(
+            &fill.order.note,
+            executor.id(),
+            fill.filled_amount,
+            fill.amount_out,
+        )
+        .unwrap();
+        expected_ids.extend(successor.map(|note| note.id()));
+    }
+
+    let mut tx_context = chain
+        .build_tx_context(executor.id(), &[maker_note.id(), taker_note.id()], &[])
+        .build();
+    set_fills(&mut tx_context, [&matched.maker, &matched.taker]);
+    let executed_tx = tx_context.execute().await.unwrap();
+
+    let output_ids: Vec<_> = executed_tx
+        .output_notes()
+        .iter()
+        .map(|note| note.id())
+        .collect();
+    assert_eq!(output_ids, expected_ids);
+
+    // both creators trade at the reference price, the executor keeps no spread
+    let vault_delta = executed_tx.account_delta().vault().fungible();
+    assert!(vault_delta.iter().all(|(_, amount)| *amount == 0));
+}
+
+#[tokio::test]
+async fn test_wrapped_price_improvement() {
+    let (mut chain, faucet_a, faucet_b) = mock_chain();
+    let creator = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let consumer = chain.add_existing_wallet(Auth::BasicAuth, vec![faucet_b.mint(100)]);
+
+    let swap_note = SwapNoteBuilder::new(
+        creator.id(),
+        faucet_a.mint(100),
+        faucet_b.mint(50),
+        get_serial_num(creator.id()),
+    )
+    .build()
+    .unwrap();
+    chain.add_pending_note(swap_note.clone());
+    chain.seal_next_block();
+
+    // an improvement of p - 19 wraps the paid amount to a single token
+    let mut tx_context = chain
+        .build_tx_context(consumer.id(), &[swap_note.id()], &[])
+        .build();
+    let note_args = [
+        Felt::new(0),
+        Felt::new(0),
+        Felt::new(0) - Felt::new(19),
+        Felt::new(20),
+    ];
+    set_note_args(
+        &mut tx_context,
+        BTreeMap::from([(swap_note.id(), note_args)]),
+    );
+    let err = tx_context.execute().await.unwrap_err();
+    // ERR_SWAP_INVALID_PRICE_IMPROVEMENT
+    assert!(format!("{:?}", err).contains("err_code: 131088"));
+}
+
+#[tokio::test]
+async fn test_expired_order() {
+    let (mut chain, faucet_a, faucet_b) = mock_chain();
+    let creator = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let consumer = chain.add_existing_wallet(Auth::BasicAuth, vec![faucet_b.mint(100)]);
+
+    let expiring_note = SwapNoteBuilder::new(
+        creator.id(),
+        faucet_a.mint(100),
+        faucet_b.mint(50),
+        get_serial_num(creator.id()),
+    )
+    .terms(OrderTerms {
+        expires_at: Some(BlockNumber::from(2)),
+        ..Default::default()
+    })
+    .build()
+    .unwrap();
+    let live_note = SwapNoteBuilder::new(
+        creator.id(),
+        faucet_a.mint(100),
+        faucet_b.mint(50),
+        get_serial_num(creator.id()),
+    )
+    .terms(OrderTerms {
+        expires_at: Some(BlockNumber::from(100)),
+        ..Default::default()
+    })
+    .build()
+    .unwrap();
+    chain.add_pending_note(expiring_note.clone());
+    chain.add_pending_note(live_note.clone());
+    chain.seal_next_block();
+    chain.seal_next_block();
+    chain.seal_next_block();
+    assert!(chain.latest_block_header().block_num() > BlockNumber::from(2));
+
+    // past its expiry only the creator can consume the order
+    let mut tx_context = chain
+        .build_tx_context(consumer.id(), &[expiring_note.id()], &[])
+        .build();
+    set_fill_amount(&mut tx_context, expiring_note.id(), 50);
+    let err = tx_context.execute().await.unwrap_err();
+    // ERR_SWAP_EXPIRED
+    assert!(format!("{:?}", err).contains("err_code: 131083"));
+
+    let tx_context = chain
+        .build_tx_context(creator.id(), &[expiring_note.id()], &[])
+        .build();
+    tx_context.execute().await.unwrap();
+
+    let mut tx_context = chain
+        .build_tx_context(consumer.id(), &[live_note.id()], &[])
+        .build();
+    set_fill_amount(&mut tx_context, live_note.id(), 50);
+    tx_context.execute().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_fill_included_by_expiry_block() {
+    let (mut chain, faucet_a, faucet_b) = mock_chain();
+    let creator = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let consumer = chain.add_existing_wallet(Auth::BasicAuth, vec![faucet_b.mint(100)]);
+
+    let order = |expires_at: u32| {
+        SwapNoteBuilder::new(
+            creator.id(),
+            faucet_a.mint(100),
+            faucet_b.mint(50),
+            get_serial_num(creator.id()),
+        )
+        .terms(OrderTerms {
+            expires_at: Some(BlockNumber::from(expires_at)),
+            ..Default::default()
+        })
+        .build()
+        .unwrap()
+    };
+    let expiring_note = order(5);
+    let distant_note = order(200_000);
+    chain.add_pending_note(expiring_note.clone());
+    chain.add_pending_note(distant_note.clone());
+    chain.seal_next_block();
+    let reference_block = chain.latest_block_header().block_num();
+    assert!(reference_block < BlockNumber::from(5));
+
+    // the reference block is before the expiry, including the fill after it must be rejected
+    let mut tx_context = chain
+        .build_tx_context(consumer.id(), &[expiring_note.id()], &[])
+        .build();
+    set_fill_amount(&mut tx_context, expiring_note.id(), 50);
+    let executed_tx = tx_context.execute().await.unwrap();
+    assert_eq!(executed_tx.expiration_block_num(), BlockNumber::from(5));
+
+    // the expiration delta is capped for orders expiring further away
+    let mut tx_context = chain
+        .build_tx_context(consumer.id(), &[distant_note.id()], &[])
+        .build();
+    set_fill_amount(&mut tx_context, distant_note.id(), 50);
+    let executed_tx = tx_context.execute().await.unwrap();
+    assert_eq!(executed_tx.expiration_block_num(), reference_block + 0xFFFF);
+
+    // a transaction referencing the expiry block can only be included after it
+    chain.seal_block(Some(5), None);
+    let mut tx_context = chain
+        .build_tx_context(consumer.id(), &[expiring_note.id()], &[])
+        .build();
+    set_fill_amount(&mut tx_context, expiring_note.id(), 50);
+    let err = tx_context.execute().await.unwrap_err();
+    // ERR_SWAP_EXPIRED
+    assert!(format!("{:?}", err).contains("err_code: 131083"));
+}
+
+#[tokio::test]
+async fn test_min_fill() {
+    let (mut chain, faucet_a, faucet_b) = mock_chain();
+    let creator = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let consumer = chain.add_existing_wallet(Auth::BasicAuth, vec![faucet_b.mint(100)]);
+
+    // 100 ETH for 50 BTC, partial fills must bring at least 20 BTC
+    let swap_note = SwapNoteBuilder::new(
+        creator.id(),
+        faucet_a.mint(100),
+        faucet_b.mint(50),
+        get_serial_num(creator.id()),
+    )
+    .terms(OrderTerms {
+        min_fill: 20,
+        ..Default::default()
+    })
+    .build()
+    .unwrap();
+    chain.add_pending_note(swap_note.clone());
+    chain.seal_next_block();
+
+    let order = RestingOrder::from_note(swap_note.clone()).unwrap();
+    assert!(!order.accepts_fill(19));
+    assert!(order.accepts_fill(20));
+
+    let mut tx_context = chain
+        .build_tx_context(consumer.id(), &[swap_note.id()], &[])
+        .build();
+    set_fill_amount(&mut tx_context, swap_note.id(), 19);
+    let err = tx_context.execute().await.unwrap_err();
+    // ERR_SWAP_FILL_BELOW_MINIMUM
+    assert!(format!("{:?}", err).contains("err_code: 131084"));
+
+    let mut tx_context = chain
+        .build_tx_context(consumer.id(), &[swap_note.id()], &[])
+        .build();
+    set_fill_amount(&mut tx_context, swap_note.id(), 20);
+    let executed_tx = tx_context.execute().await.unwrap();
+
+    // the SWAPp' keeps the minimum, its remaining 30 BTC can still be filled at once
+    let successor = create_swap_note_successor(&swap_note, consumer.id(), 20, 40)
+        .unwrap()
+        .unwrap();
+    assert_eq!(executed_tx.output_notes().get_note(1).id(), successor.id());
+    let successor = RestingOrder::from_note(successor).unwrap();
+    assert_eq!(successor.terms().min_fill, 20);
+    assert!(successor.accepts_fill(30));
+}
+
+#[tokio::test]
+async fn test_all_or_none_order() {
+    let (mut chain, faucet_a, faucet_b) = mock_chain();
+    let creator = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let consumer = chain.add_existing_wallet(Auth::BasicAuth, vec![faucet_b.mint(100)]);
+
+    let swap_note = SwapNoteBuilder::new(
+        creator.id(),
+        faucet_a.mint(100),
+        faucet_b.mint(50),
+        get_serial_num(creator.id()),
+    )
+    .terms(OrderTerms {
+        time_in_force: TimeInForce::AllOrNone,
+        ..Default::default()
+    })
+    .build()
+    .unwrap();
+    chain.add_pending_note(swap_note.clone());
+    chain.seal_next_block();
+
+    let mut tx_context = chain
+        .build_tx_context(consumer.id(), &[swap_note.id()], &[])
+        .build();
+    set_fill_amount(&mut tx_context, swap_note.id(), 49);
+    let err = tx_context.execute().await.unwrap_err();
+    // ERR_SWAP_PARTIAL_FILL_NOT_ALLOWED
+    assert!(format!("{:?}", err).contains("err_code: 131085"));
+
+    let mut tx_context = chain
+        .build_tx_context(consumer.id(), &[swap_note.id()], &[])
+        .build();
+    set_fill_amount(&mut tx_context, swap_note.id(), 50);
+    let executed_tx = tx_context.execute().await.unwrap();
+    assert_eq!(executed_tx.output_notes().num_notes(), 1);
+}
+
+#[tokio::test]
+async fn test_authorized_executor() {
+    let (mut chain, faucet_a, faucet_b) = mock_chain();
+    let creator = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let executor = chain.add_existing_wallet(Auth::BasicAuth, vec![faucet_b.mint(100)]);
+    let stranger = chain.add_existing_wallet(Auth::BasicAuth, vec![faucet_b.mint(100)]);
+
+    let swap_note = SwapNoteBuilder::new(
+        creator.id(),
+        faucet_a.mint(100),
+        faucet_b.mint(50),
+        get_serial_num(creator.id()),
+    )
+    .terms(OrderTerms {
+        executor: Some(executor.id()),
+        ..Default::default()
+    })
+    .build()
+    .unwrap();
+    chain.add_pending_note(swap_note.clone());
+    chain.seal_next_block();
+
+    let order = RestingOrder::from_note(swap_note.clone()).unwrap();
+    assert_eq!(order.terms().executor, Some(executor.id()));
+    assert!(!order.can_be_filled_by(stranger.id()));
+
+    let mut tx_context = chain
+        .build_tx_context(stranger.id(), &[swap_note.id()], &[])
+        .build();
+    set_fill_amount(&mut tx_context, swap_note.id(), 20);
+    let err = tx_context.execute().await.unwrap_err();
+    // ERR_SWAP_UNAUTHORIZED_EXECUTOR
+    assert!(format!("{:?}", err).contains("err_code: 131086"));
+
+    let mut tx_context = chain
+        .build_tx_context(executor.id(), &[swap_note.id()], &[])
+        .build();
+    set_fill_amount(&mut tx_context, swap_note.id(), 20);
+    let executed_tx = tx_context.execute().await.unwrap();
+
+    // the SWAPp' stays restricted to the executor
+    let successor = create_swap_note_successor(&swap_note, executor.id(), 20, 40)
+        .unwrap()
+        .unwrap();
+    assert_eq!(executed_tx.output_notes().get_note(1).id(), successor.id());
+    let (p2id_details, _) = reconstruct_p2id_note(&swap_note, 0, 20).unwrap();
+    assert_eq!(
+        executed_tx.output_notes().get_note(0).id(),
+        p2id_details.id()
+    );
+}
+
+#[tokio::test]
+async fn test_fee_note() {
+    let (mut chain, faucet_a, faucet_b) = mock_chain();
+    let creator = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let consumer = chain.add_existing_wallet(Auth::BasicAuth, vec![faucet_b.mint(100_000)]);
+    let pool = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+
+    // 30 basis points of every fill go to the pool
+    let swap_note = SwapNoteBuilder::new(
+        creator.id(),
+        faucet_a.mint(100_000),
+        faucet_b.mint(50_000),
+        get_serial_num(creator.id()),
+    )
+    .terms(OrderTerms {
+        fee: Some(OrderFee {
+            bps: 30,
+            recipient: pool.id(),
+        }),
+        ..Default::default()
+    })
+    .build()
+    .unwrap();
+    chain.add_pending_note(swap_note.clone());
+    chain.seal_next_block();
+
+    let filled_amount = 20_003;
+    let fee = calculate_fee(filled_amount, 30);
+    assert_eq!(fee, 60);
+
+    let mut tx_context = chain
+        .build_tx_context(consumer.id(), &[swap_note.id()], &[])
+        .build();
+    set_fill_amount(&mut tx_context, swap_note.id(), filled_amount);
+    let executed_tx = tx_context.execute().await.unwrap();
+    let output_notes = executed_tx.output_notes();
+    assert_eq!(output_notes.num_notes(), 3);
+
+    let (p2id_details, _) = reconstruct_p2id_note(&swap_note, 0, filled_amount).unwrap();
+    assert_eq!(output_notes.get_note(0).id(), p2id_details.id());
+    assert_eq!(
+        p2id_details.assets().iter().next(),
+        Some(&faucet_b.mint(filled_amount - fee))
+    );
+
+    let (fee_recipient, fee_details, fee_tag) = reconstruct_fee_note(&swap_note, 0, filled_amount)
+        .unwrap()
+        .unwrap();
+    assert_eq!(fee_recipient, pool.id());
+    assert_eq!(output_notes.get_note(1).id(), fee_details.id());
+    assert_eq!(output_notes.get_note(1).metadata().tag(), fee_tag);
+    assert_eq!(
+        fee_details.assets().iter().next(),
+        Some(&faucet_b.mint(fee))
+    );
+
+    let amount_out = calculate_tokens_a_for_b(100_000, 50_000, filled_amount).unwrap();
+    let successor =
+        create_swap_note_successor(&swap_note, consumer.id(), filled_amount, amount_out)
+            .unwrap()
+            .unwrap();
+    assert_eq!(output_notes.get_note(2).id(), successor.id());
+}
+
+#[tokio::test]
+async fn test_fee_note_paid_to_creator_differs_from_payout() {
+    let (mut chain, faucet_a, faucet_b) = mock_chain();
+    let creator = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let consumer = chain.add_existing_wallet(Auth::BasicAuth, vec![faucet_b.mint(100_000)]);
+
+    // half of every fill goes back to the creator as the fee: same recipient, same amount
+    let swap_note = SwapNoteBuilder::new(
+        creator.id(),
+        faucet_a.mint(100_000),
+        faucet_b.mint(50_000),
+        get_serial_num(creator.id()),
+    )
+    .terms(OrderTerms {
+        fee: Some(OrderFee {
+            bps: 5000,
+            recipient: creator.id(),
+        }),
+        ..Default::default()
+    })
+    .build()
+    .unwrap();
+    chain.add_pending_note(swap_note.clone());
+    chain.seal_next_block();
+
+    let filled_amount = 20_000;
+    let mut tx_context = chain
+        .build_tx_context(consumer.id(), &[swap_note.id()], &[])
+        .build();
+    set_fill_amount(&mut tx_context, swap_note.id(), filled_amount);
+    let executed_tx = tx_context.execute().await.unwrap();
+    let output_notes = executed_tx.output_notes();
+
+    let (p2id_details, _) = reconstruct_p2id_note(&swap_note, 0, filled_amount).unwrap();
+    let (_, fee_details, _) = reconstruct_fee_note(&swap_note, 0, filled_amount)
+        .unwrap()
+        .unwrap();
+    assert_eq!(p2id_details.assets(), fee_details.assets());
+    assert_ne!(p2id_details.id(), fee_details.id());
+    assert_eq!(output_notes.get_note(0).id(), p2id_details.id());
+    assert_eq!(output_notes.get_note(1).id(), fee_details.id());
+}
+
+#[tokio::test]
+async fn test_recover_payout_of_later_fill_with_fee() {
+    let (mut chain, faucet_a, faucet_b) = mock_chain();
+    let creator = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let consumer = chain.add_existing_wallet(Auth::BasicAuth, vec![faucet_b.mint(100_000)]);
+    let pool = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+
+    let swap_note = SwapNoteBuilder::new(
+        creator.id(),
+        faucet_a.mint(100_000),
+        faucet_b.mint(50_000),
+        get_serial_num(creator.id()),
+    )
+    .terms(OrderTerms {
+        fee: Some(OrderFee {
+            bps: 30,
+            recipient: pool.id(),
+        }),
+        ..Default::default()
+    })
+    .build()
+    .unwrap();
+    chain.add_pending_note(swap_note.clone());
+    chain.seal_next_block();
+
+    // the first fill leaves a SWAPp' with 30_000 requested
+    let first_fill = 20_000;
+    let amount_out = calculate_tokens_a_for_b(100_000, 50_000, first_fill).unwrap();
+    let successor = create_swap_note_successor(&swap_note, consumer.id(), first_fill, amount_out)
+        .unwrap()
+        .unwrap();
+    chain.add_pending_note(successor.clone());
+    chain.seal_next_block();
+
+    // the payout of the second fill is recovered from the original order and its fill number
+    let filled_amount = 10_007;
+    let fee = calculate_fee(filled_amount, 30);
+    assert_eq!(fee, 30);
+
+    let mut tx_context = chain
+        .build_tx_context(consumer.id(), &[successor.id()], &[])
+        .build();
+    set_fill_amount(&mut tx_context, successor.id(), filled_amount);
+    let executed_tx = tx_context.execute().await.unwrap();
+
+    let (p2id_details, p2id_tag) = reconstruct_p2id_note(&swap_note, 1, filled_amount).unwrap();
+    let p2id_note = executed_tx.output_notes().get_note(0);
+    assert_eq!(p2id_note.id(), p2id_details.id());
+    assert_eq!(p2id_note.metadata().tag(), p2id_tag);
+    assert_eq!(
+        p2id_details.assets().iter().next(),
+        Some(&faucet_b.mint(filled_amount - fee))
+    );
+
+    // the amount received after the fee does not rebuild the payout
+    let (received_details, _) = reconstruct_p2id_note(&swap_note, 1, filled_amount - fee).unwrap();
+    assert_ne!(received_details.id(), p2id_details.id());
+}
+
+#[tokio::test]
+async fn test_payout_account() {
+    let (mut chain, faucet_a, faucet_b) = mock_chain();
+    let creator = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let cold_wallet = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let consumer = chain.add_existing_wallet(Auth::BasicAuth, vec![faucet_b.mint(100)]);
+
+    // proceeds of the order go to the cold wallet instead of the creator
+    let swap_note = SwapNoteBuilder::new(
+        creator.id(),
+        faucet_a.mint(100),
+        faucet_b.mint(50),
+        get_serial_num(creator.id()),
+    )
+    .terms(OrderTerms {
+        payout: Some(cold_wallet.id()),
+        ..Default::default()
+    })
+    .build()
+    .unwrap();
+    chain.add_pending_note(swap_note.clone());
+    chain.seal_next_block();
+
+    let filled_amount = 20;
+    let mut tx_context = chain
+        .build_tx_context(consumer.id(), &[swap_note.id()], &[])
+        .build();
+    set_fill_amount(&mut tx_context, swap_note.id(), filled_amount);
+    let executed_tx = tx_context.execute().await.unwrap();
+
+    let (p2id_details, p2id_tag) = reconstruct_p2id_note(&swap_note, 0, filled_amount).unwrap();
+    assert_eq!(
+        p2id_tag,
+        NoteTag::from_account_id(cold_wallet.id(), NoteExecutionMode::Local).unwrap()
+    );
+
+    let p2id_note = executed_tx.output_notes().get_note(0);
+    assert_eq!(p2id_note.id(), p2id_details.id());
+    assert_eq!(p2id_note.metadata().tag(), p2id_tag);
+
+    // the successor keeps paying out to the cold wallet
+    let amount_out = calculate_tokens_a_for_b(100, 50, filled_amount).unwrap();
+    let successor =
+        create_swap_note_successor(&swap_note, consumer.id(), filled_amount, amount_out)
+            .unwrap()
+            .unwrap();
+    assert_eq!(executed_tx.output_notes().get_note(1).id(), successor.id());
+}
+
+#[tokio::test]
+async fn test_successor_keeps_aux_and_execution_hint() {
+    let (mut chain, faucet_a, faucet_b) = mock_chain();
+    let creator = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let consumer = chain.add_existing_wallet(Auth::BasicAuth, vec![faucet_b.mint(100)]);
+
+    let execution_hint = NoteExecutionHint::on_block_slot(10, 7, 1);
+    let swap_note = SwapNoteBuilder::new(
+        creator.id(),
+        faucet_a.mint(100),
+        faucet_b.mint(50),
+        get_serial_num(creator.id()),
+    )
+    .terms(OrderTerms {
+        aux: Felt::new(7),
+        execution_hint,
+        ..Default::default()
+    })
+    .build()
+    .unwrap();
+    assert_eq!(swap_note.metadata().aux(), Felt::new(7));
+    assert_eq!(swap_note.metadata().execution_hint(), execution_hint);
+    chain.add_pending_note(swap_note.clone());
+    chain.seal_next_block();
+
+    let filled_amount = 20;
+    let mut tx_context = chain
+        .build_tx_context(consumer.id(), &[swap_note.id()], &[])
+        .build();
+    set_fill_amount(&mut tx_context, swap_note.id(), filled_amount);
+    let executed_tx = tx_context.execute().await.unwrap();
+
+    let amount_out = calculate_tokens_a_for_b(100, 50, filled_amount).unwrap();
+    let successor =
+        create_swap_note_successor(&swap_note, consumer.id(), filled_amount, amount_out)
+            .unwrap()
+            .unwrap();
+
+    let output_note = executed_tx.output_notes().get_note(1);
+    assert_eq!(output_note.id(), successor.id());
+    assert_eq!(output_note.metadata(), successor.metadata());
+    assert_eq!(output_note.metadata().aux(), Felt::new(7));
+    assert_eq!(output_note.metadata().execution_hint(), execution_hint);
+}
+
+#[test]
+fn test_swap_note_inputs_round_trip() {
+    let creator = AccountId::try_from(ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE).unwrap();
+    let other = AccountId::try_from(ACCOUNT_ID_PRIVATE_SENDER).unwrap();
+    let faucet = AccountId::try_from(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET).unwrap();
+
+    let inputs = SwapNoteInputs {
+        requested: FungibleAsset::new(faucet, 50).unwrap(),
+        swapp_tag: NoteTag::from(0x1234),
+        fill_number: 3,
+        creator,
+        terms: OrderTerms {
+            expires_at: Some(BlockNumber::from(100)),
+            min_fill: 5,
+            time_in_force: TimeInForce::AllOrNone,
+            executor: Some(other),
+            fee: Some(OrderFee {
+                bps: 25,
+                recipient: other,
+            }),
+            payout: Some(other),
+            aux: Felt::new(9),
+            execution_hint: NoteExecutionHint::after_block(BlockNumber::from(7)).unwrap(),
+            pegged: true,
+        },
+    };
+
+    let encoded = inputs.encode().unwrap();
+    assert_eq!(encoded.num_values() as usize, SWAPP_NUM_INPUTS);
+    assert_eq!(SwapNoteInputs::decode(&encoded).unwrap(), inputs);
+
+    let defaults = SwapNoteInputs {
+        terms: OrderTerms::default(),
+        ..inputs
+    };
+    assert_eq!(
+        SwapNoteInputs::decode(&defaults.encode().unwrap()).unwrap(),
+        defaults
+    );
+
+    let invalid_fee = SwapNoteInputs {
+        terms: OrderTerms {
+            fee: Some(OrderFee {
+                bps: 10_000,
+                recipient: other,
+            }),
+            ..Default::default()
+        },
+        ..inputs
+    };
+    assert!(matches!(
+        invalid_fee.encode(),
+        Err(SwapNoteError::InvalidFee(10_000))
+    ));
+
+    let too_short = NoteInputs::new(encoded.values()[..SWAPP_NUM_INPUTS - 1].to_vec()).unwrap();
+    assert!(matches!(
+        SwapNoteInputs::decode(&too_short),
+        Err(SwapNoteError::InvalidInputs(_))
+    ));
+}
+
+#[test]
+fn test_swap_note_builder() {
+    let creator = AccountId::try_from(ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE).unwrap();
+    let offered = FungibleAsset::new(
+        AccountId::try_from(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET).unwrap(),
+        100,
+    )
+    .unwrap();
+    let requested = FungibleAsset::new(
+        AccountId::try_from(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1).unwrap(),
+        50,
+    )
+    .unwrap();
+    let serial_num = [Felt::new(1); 4];
+
+    let private_note = SwapNoteBuilder::new(creator, offered.into(), requested.into(), serial_num)
+        .build()
+        .unwrap();
+    let public_note = SwapNoteBuilder::new(creator, offered.into(), requested.into(), serial_num)
+        .note_type(NoteType::Public)
+        .tag(NoteTag::from(42))
+        .build()
+        .unwrap();
+
+    assert_eq!(private_note.metadata().note_type(), NoteType::Private);
+    assert_eq!(public_note.metadata().note_type(), NoteType::Public);
+    assert_eq!(public_note.metadata().tag(), NoteTag::from(42));
+    assert_eq!(public_note.metadata().sender(), creator);
+    assert_ne!(private_note.script().root(), public_note.script().root());
+
+    let inputs = SwapNoteInputs::decode(public_note.inputs()).unwrap();
+    assert_eq!(inputs.requested, requested);
+    assert_eq!(inputs.swapp_tag, NoteTag::from(42));
+    assert_eq!(inputs.creator, creator);
+
+    assert!(matches!(
+        SwapNoteBuilder::new(creator, offered.into(), requested.into(), serial_num)
+            .note_type(NoteType::Encrypted)
+            .build(),
+        Err(SwapNoteError::UnsupportedNoteType(NoteType::Encrypted))
+    ));
+}
+
+#[tokio::test]
+async fn test_public_swap_note() {
+    let (mut chain, faucet_a, faucet_b) = mock_chain();
+    let creator = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let consumer = chain.add_existing_wallet(Auth::BasicAuth, vec![faucet_b.mint(100)]);
+
+    let swap_note = SwapNoteBuilder::new(
+        creator.id(),
+        faucet_a.mint(100),
+        faucet_b.mint(50),
+        get_serial_num(creator.id()),
+    )
+    .note_type(NoteType::Public)
+    .build()
+    .unwrap();
+    chain.add_pending_note(swap_note.clone());
+    chain.seal_next_block();
+
+    let filled_amount = 20;
+    let (p2id_details, p2id_tag) = reconstruct_p2id_note(&swap_note, 0, filled_amount).unwrap();
+    let amount_out = calculate_tokens_a_for_b(100, 50, filled_amount).unwrap();
+    let successor =
+        create_swap_note_successor(&swap_note, consumer.id(), filled_amount, amount_out)
+            .unwrap()
+            .unwrap();
+
+    // public output notes are created with their full details
+    let p2id_note = Note::new(
+        p2id_details.assets().clone(),
+        NoteMetadata::new(
+            consumer.id(),
+            NoteType::Public,
+            p2id_tag,
+            NoteExecutionHint::always(),
+            Felt::new(0),
+        )
+        .unwrap(),
+        p2id_details.recipient().clone(),
+    );
+    let mut tx_context = chain
+        .build_tx_context(consumer.id(), &[swap_note.id()], &[])
+        .expected_notes(vec![
+            OutputNote::Full(p2id_note.clone()),
+            OutputNote::Full(successor.clone()),
+        ])
+        .build();
+    set_fill_amount(&mut tx_context, swap_note.id(), filled_amount);
+    let executed_tx = tx_context.execute().await.unwrap();
+    let output_notes = executed_tx.output_notes();
+
+    assert_eq!(output_notes.get_note(0).id(), p2id_note.id());
+    assert_eq!(output_notes.get_note(0).metadata(), p2id_note.metadata());
+    assert_eq!(output_notes.get_note(1).id(), successor.id());
+    assert_eq!(output_notes.get_note(1).metadata(), successor.metadata());
+}
+
+#[test]
+fn test_swap_order_from_note() {
+    let creator = AccountId::try_from(ACCOUNT_ID_REGULAR_PUBLIC_ACCOUNT_UPDATABLE_CODE).unwrap();
+    let eth = AccountId::try_from(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET).unwrap();
+    let btc = AccountId::try_from(ACCOUNT_ID_PUBLIC_FUNGIBLE_FAUCET_1).unwrap();
+    let pair = TradingPair::new(eth, btc);
+    assert_eq!(pair, TradingPair::new(btc, eth));
+
+    // sells 100 base for 50 quote, and buys 30 base for 60 quote
+    let sell_note = SwapNoteBuilder::new(
+        creator,
+        FungibleAsset::new(pair.base, 100).unwrap().into(),
+        FungibleAsset::new(pair.quote, 50).unwrap().into(),
+        [Felt::new(1); 4],
+    )
+    .fill_number(2)
+    .terms(OrderTerms {
+        min_fill: 10,
+        ..Default::default()
+    })
+    .build()
+    .unwrap();
+    let buy_note = SwapNoteBuilder::new(
+        creator,
+        FungibleAsset::new(pair.quote, 60).unwrap().into(),
+        FungibleAsset::new(pair.base, 30).unwrap().into(),
+        [Felt::new(2); 4],
+    )
+    .note_type(NoteType::Public)
+    .build()
+    .unwrap();
+
+    let sell = SwapOrder::try_from(&sell_note).unwrap();
+    assert_eq!(sell.offered, FungibleAsset::new(pair.base, 100).unwrap());
+    assert_eq!(
+        sell.requested(),
+        FungibleAsset::new(pair.quote, 50).unwrap()
+    );
+    assert_eq!(sell.creator(), creator);
+    assert_eq!(sell.fill_number(), 2);
+    assert_eq!(sell.swapp_tag(), sell_note.metadata().tag());
+    assert_eq!(sell.terms().min_fill, 10);
+    assert_eq!(sell.note_type, NoteType::Private);
+    assert_eq!(sell.pair(), pair);
+    assert_eq!(sell.side(), Side::Sell);
+    assert_eq!(sell.price(), 0.5);
+
+    let buy = SwapOrder::try_from(&buy_note).unwrap();
+    assert_eq!(buy.note_type, NoteType::Public);
+    assert_eq!(buy.pair(), pair);
+    assert_eq!(buy.side(), Side::Buy);
+    assert_eq!(buy.price(), 2.0);
+
+    // same inputs and assets, but another script
+    let script =
+        NoteScript::compile("begin push.1 drop end", TransactionKernel::assembler()).unwrap();
+    let other_note = Note::new(
+        sell_note.assets().clone(),
+        *sell_note.metadata(),
+        NoteRecipient::new(sell_note.serial_num(), script, sell_note.inputs().clone()),
+    );
+    assert!(matches!(
+        SwapOrder::try_from(&other_note),
+        Err(SwapNoteError::UnknownScript)
+    ));
+
+    // SWAPp script with a truncated input layout
+    let inputs = NoteInputs::new(sell_note.inputs().values()[..14].to_vec()).unwrap();
+    let truncated_note = Note::new(
+        sell_note.assets().clone(),
+        *sell_note.metadata(),
+        NoteRecipient::new(sell_note.serial_num(), sell_note.script().clone(), inputs),
+    );
+    assert!(matches!(
+        SwapOrder::try_from(&truncated_note),
+        Err(SwapNoteError::InvalidInputs(14))
+    ));
+}
+
+#[tokio::test]
+async fn test_settle_public_against_private_order() {
+    let (mut chain, faucet_a, faucet_b) = mock_chain();
+    let alice = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let bob = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let executor = chain.add_existing_wallet(Auth::BasicAuth, vec![faucet_b.mint(50)]);
+
+    // alice posts a public order, bob a private one
+    let maker_note = SwapNoteBuilder::new(
+        alice.id(),
+        faucet_a.mint(100),
+        faucet_b.mint(50),
+        get_serial_num(alice.id()),
+    )
+    .note_type(NoteType::Public)
+    .build()
+    .unwrap();
+    let taker_note = SwapNoteBuilder::new(
+        bob.id(),
+        faucet_b.mint(60),
+        faucet_a.mint(110),
+        get_serial_num(bob.id()),
+    )
+    .build()
+    .unwrap();
+    chain.add_pending_note(maker_note.clone());
+    chain.add_pending_note(taker_note.clone());
+    chain.seal_next_block();
+
+    let maker = RestingOrder::from_note(maker_note.clone()).unwrap();
+    let taker = RestingOrder::from_note(taker_note.clone()).unwrap();
+    let matched = compute_fills(&maker, &taker).unwrap();
+
+    let mut expected_notes = Vec::new();
+    for fill in [&matched.maker, &matched.taker] {
+        let (details, tag) =
+            reconstruct_p2id_note(&fill.order.note, 0, fill.filled_amount).unwrap();
+        let metadata = NoteMetadata::new(
+            executor.id(),
+            fill.order.note.metadata().note_type(),
+            tag,
+            NoteExecutionHint::always(),
+            Felt::new(0),
+        )
+        .unwrap();
+        expected_notes.push(Note::new(
+            details.assets().clone(),
+            metadata,
+            details.recipient().clone(),
+        ));
+
+        let successor = create_swap_note_successor(
+            &fill.order.note,
+            executor.id(),
+            fill.filled_amount,
+            fill.amount_out,
+        )
+        .unwrap();
+        expected_notes.extend(successor);
+    }
+
+    let mut tx_context = chain
+        .build_tx_context(executor.id(), &[maker_note.id(), taker_note.id()], &[])
+        .expected_notes(
+            expected_notes
+                .iter()
+                .cloned()
+                .map(OutputNote::Full)
+                .collect(),
+        )
+        .build();
+    set_fills(&mut tx_context, [&matched.maker, &matched.taker]);
+    let executed_tx = tx_context.execute().await.unwrap();
+
+    let output_notes: Vec<_> = executed_tx
+        .output_notes()
+        .iter()
+        .map(|note| (note.id(), note.metadata().note_type()))
+        .collect();
+    let expected: Vec<_> = expected_notes
+        .iter()
+        .map(|note| (note.id(), note.metadata().note_type()))
+        .collect();
+    assert_eq!(output_notes, expected);
+    assert_eq!(output_notes[0].1, NoteType::Public);
+}
+
+#[test]
+fn test_swap_note_script_roots() {
+    for (note_type, root) in [
+        (NoteType::Public, PUBLIC_SWAPP_SCRIPT_ROOT),
+        (NoteType::Private, PRIVATE_SWAPP_SCRIPT_ROOT),
+    ] {
+        for profile in [ScriptProfile::Debug, ScriptProfile::Production] {
+            let script = compile_swap_note_script(note_type, profile).unwrap();
+            assert_eq!(script.root().to_hex(), root);
+        }
+        assert_eq!(swap_note_script(note_type).unwrap().root().to_hex(), root);
+    }
+}
+
+#[tokio::test]
+async fn test_settle_ring_in_one_transaction() {
+    let (mut chain, faucet_a, faucet_b) = mock_chain();
+    let faucet_c = chain.add_existing_faucet(Auth::BasicAuth, "USD", 1_000_000, None);
+    let alice = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let bob = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let carol = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let executor = chain.add_existing_wallet(Auth::BasicAuth, vec![faucet_b.mint(50)]);
+
+    // ETH -> BTC, USD -> ETH and BTC -> USD
+    let notes: Vec<Note> = [
+        (&alice, faucet_a.mint(100), faucet_b.mint(50)),
+        (&carol, faucet_c.mint(40), faucet_a.mint(70)),
+        (&bob, faucet_b.mint(60), faucet_c.mint(30)),
+    ]
+    .into_iter()
+    .map(|(creator, offered, requested)| {
+        SwapNoteBuilder::new(
+            creator.id(),
+            offered,
+            requested,
+            get_serial_num(creator.id()),
+        )
+        .build()
+        .unwrap()
+    })
+    .collect();
+    for note in &notes {
+        chain.add_pending_note(note.clone());
+    }
+    chain.seal_next_block();
+
+    let orders: Vec<RestingOrder> = notes
+        .iter()
+        .map(|note| RestingOrder::from_note(note.clone()).unwrap())
+        .collect();
+    let ring = compute_ring_fills(&orders.iter().collect::<Vec<_>>()).unwrap();
+
+    let mut expected_ids = Vec::new();
+    for fill in &ring.fills {
+        let (p2id_details, _) =
+            reconstruct_p2id_note(&fill.order.note, 0, fill.filled_amount).unwrap();
+        expected_ids.push(p2id_details.id());
+
+        let successor = create_swap_note_successor(
+            &fill.order.note,
+            executor.id(),
+            fill.filled_amount,
+            fill.amount_out,
+        )
+        .unwrap();
+        expected_ids.extend(successor.map(|note| note.id()));
+    }
+
+    let note_ids: Vec<NoteId> = notes.iter().map(Note::id).collect();
+    let mut tx_context = chain
+        .build_tx_context(executor.id(), &note_ids, &[])
+        .build();
+    set_fills(&mut tx_context, &ring.fills);
+    let executed_tx = tx_context.execute().await.unwrap();
+
+    let output_ids: Vec<_> = executed_tx
+        .output_notes()
+        .iter()
+        .map(|note| note.id())
+        .collect();
+    assert_eq!(output_ids, expected_ids);
+
+    // the executor paid alice 50 BTC and got 60 back from bob
+    let gain_b = executed_tx
+        .account_delta()
+        .vault()
+        .fungible()
+        .iter()
+        .find(|(faucet_id, _)| **faucet_id == faucet_b.id())
+        .map(|(_, amount)| *amount);
+    assert_eq!(gain_b, Some(10));
+}
+
+#[tokio::test]
+async fn test_settle_multi_match_in_one_transaction() {
+    let (mut chain, faucet_a, faucet_b) = mock_chain();
+    let alice = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let bob = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let carol = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let executor = chain.add_existing_wallet(Auth::BasicAuth, vec![faucet_b.mint(30)]);
+
+    // alice and bob sell ETH at 2 ETH per BTC, carol sells 30 BTC for 45 ETH
+    let notes: Vec<Note> = [
+        (&alice, faucet_a.mint(40), faucet_b.mint(20)),
+        (&bob, faucet_a.mint(30), faucet_b.mint(15)),
+        (&carol, faucet_b.mint(30), faucet_a.mint(45)),
+    ]
+    .into_iter()
+    .map(|(creator, offered, requested)| {
+        SwapNoteBuilder::new(
+            creator.id(),
+            offered,
+            requested,
+            get_serial_num(creator.id()),
+        )
+        .build()
+        .unwrap()
+    })
+    .collect();
+    for note in &notes {
+        chain.add_pending_note(note.clone());
+    }
+    chain.seal_next_block();
+
+    let orders: Vec<RestingOrder> = notes
+        .iter()
+        .map(|note| RestingOrder::from_note(note.clone()).unwrap())
+        .collect();
+    let multi_match = compute_multi_fills(&[&orders[0], &orders[1]], &orders[2]).unwrap();
+    assert!(multi_match.taker.is_complete());
+
+    let mut expected_ids = Vec::new();
+    for fill in multi_match.makers.iter().chain([&multi_match.taker]) {
+        let (p2id_details, _) =
+            reconstruct_p2id_note(&fill.order.note, 0, fill.filled_amount).unwrap();
+        expected_ids.push(p2id_details.id());
+
+        let successor = create_swap_note_successor(
+            &fill.order.note,
+            executor.id(),
+            fill.filled_amount,
+            fill.amount_out,
+        )
+        .unwrap();
+        expected_ids.extend(successor.map(|note| note.id()));
+    }
+
+    let note_ids: Vec<NoteId> = notes.iter().map(Note::id).collect();
+    let mut tx_context = chain
+        .build_tx_context(executor.id(), &note_ids, &[])
+        .build();
+    set_fills(
+        &mut tx_context,
+        multi_match.makers.iter().chain([&multi_match.taker]),
+    );
+    let executed_tx = tx_context.execute().await.unwrap();
+
+    let output_ids: Vec<_> = executed_tx
+        .output_notes()
+        .iter()
+        .map(|note| note.id())
+        .collect();
+    assert_eq!(output_ids, expected_ids);
+
+    // the executor received 60 ETH from the makers and paid 45 to carol
+    let gain_a = executed_tx
+        .account_delta()
+        .vault()
+        .fungible()
+        .iter()
+        .find(|(faucet_id, _)| **faucet_id == faucet_a.id())
+        .map(|(_, amount)| *amount);
+    assert_eq!(gain_a, Some(15));
+}
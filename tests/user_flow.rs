@@ -7,7 +7,7 @@ use tokio::time::sleep;
 use miden_client::transaction::TransactionRequestBuilder;
 use miden_dark_pool::cli::open_order::get_serial_num;
 use miden_dark_pool::utils::common::client_setup;
-use miden_dark_pool::utils::common::create_partial_swap_note;
+use miden_dark_pool::utils::swap_note::SwapNoteBuilder;
 use miden_objects::asset::FungibleAsset;
 use miden_objects::transaction::OutputNote;
 
@@ -44,7 +44,7 @@ async fn test_open_order() {
     let user = setup_test_user(
         &mut client,
         keystore,
-        &format!("testuser"),
+        "testuser",
         faucet_a.clone(),
         faucet_b.clone(),
         100,
@@ -106,7 +106,7 @@ async fn test_cancel_order() {
     let user = setup_test_user(
         &mut client,
         keystore,
-        &format!("testuser"),
+        "testuser",
         faucet_a.clone(),
         faucet_b.clone(),
         100,
@@ -131,14 +131,14 @@ async fn test_cancel_order() {
     let fill_number = 0;
 
     // Create the partial swap note
-    let swap_note = create_partial_swap_note(
-        user.account_id.id(),
+    let swap_note = SwapNoteBuilder::new(
         user.account_id.id(),
         asset_a.into(),
         asset_b.into(),
         serial_num,
-        fill_number,
     )
+    .fill_number(fill_number)
+    .build()
     .unwrap();
 
     let note_req = TransactionRequestBuilder::new()
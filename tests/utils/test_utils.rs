@@ -1,24 +1,18 @@
+use std::collections::BTreeMap;
+
 use miden_client::Client;
-use miden_client::account::Account;
+use miden_client::account::{Account, AccountStorageMode};
 use miden_client::keystore::FilesystemKeyStore;
+use miden_objects::note::NoteId;
+use miden_objects::transaction::TransactionArgs;
+use miden_objects::vm::AdviceMap;
+use miden_objects::{Felt, Word};
+use miden_tx::testing::{Auth, MockChain, MockFungibleFaucet, TransactionContext};
 use rand::rngs::StdRng;
 
-use miden_client::ClientError;
-use miden_client::account::{
-    AccountBuilder, AccountStorageMode, AccountType, component::BasicFungibleFaucet,
-    component::BasicWallet, component::RpoFalcon512,
-};
-
-use miden_objects::note::NoteType;
-use miden_objects::{Felt, asset::FungibleAsset};
-
-use miden_client::{
-    asset::TokenSymbol, auth::AuthSecretKey, crypto::SecretKey,
-    transaction::TransactionRequestBuilder,
-};
-
-use rand::RngCore;
-use std::time::Duration;
+use miden_dark_pool::matching::book::SwapFill;
+use miden_dark_pool::utils::common::{AccountSetupError, create_wallet};
+use miden_dark_pool::utils::dev::{self, DevError, TokenConfig};
 
 #[derive(Debug, Clone)]
 pub struct TestUser {
@@ -29,35 +23,8 @@ pub struct TestUser {
 pub async fn create_account(
     client: &mut Client,
     keystore: FilesystemKeyStore<rand::prelude::StdRng>,
-) -> Result<Account, ClientError> {
-    let mut init_seed = [0_u8; 32];
-    client.rng().fill_bytes(&mut init_seed);
-
-    let key_pair = SecretKey::with_rng(client.rng());
-
-    // Anchor block
-    let anchor_block = client.get_latest_epoch_block().await.unwrap();
-
-    // Build the account
-    let builder = AccountBuilder::new(init_seed)
-        .anchor((&anchor_block).try_into().unwrap())
-        .account_type(AccountType::RegularAccountUpdatableCode)
-        .storage_mode(AccountStorageMode::Public)
-        .with_component(RpoFalcon512::new(key_pair.public_key()))
-        .with_component(BasicWallet);
-
-    let (alice_account, seed) = builder.build().unwrap();
-
-    // Add the account to the client
-    client
-        .add_account(&alice_account, Some(seed), false)
-        .await?;
-
-    // Add the key pair to the keystore
-    keystore
-        .add_key(&AuthSecretKey::RpoFalcon512(key_pair))
-        .unwrap();
-    Ok(alice_account)
+) -> Result<Account, AccountSetupError> {
+    create_wallet(client, &keystore, AccountStorageMode::Public).await
 }
 
 // TODO: Not a dead code
@@ -66,45 +33,15 @@ pub async fn create_faucet(
     client: &mut Client,
     keystore: FilesystemKeyStore<rand::prelude::StdRng>,
     symbol: &str,
-) -> Result<Account, ClientError> {
-    // Faucet seed
-    let mut init_seed = [0u8; 32];
-    client.rng().fill_bytes(&mut init_seed);
-
-    // Faucet parameters
-    let symbol = TokenSymbol::new(symbol).unwrap();
-    let decimals = 8;
-    let max_supply = Felt::new(1_000_000);
-
-    // Generate key pair
-    let key_pair = SecretKey::with_rng(client.rng());
-
-    let anchor_block = client.get_latest_epoch_block().await.unwrap();
-
-    // Build the account
-    let builder = AccountBuilder::new(init_seed)
-        .anchor((&anchor_block).try_into().unwrap())
-        .account_type(AccountType::FungibleFaucet)
-        .storage_mode(AccountStorageMode::Public)
-        .with_component(RpoFalcon512::new(key_pair.public_key()))
-        .with_component(BasicFungibleFaucet::new(symbol, decimals, max_supply).unwrap());
-
-    let (faucet_account, seed) = builder.build().unwrap();
-
-    // Add the faucet to the client
-    client
-        .add_account(&faucet_account, Some(seed), false)
-        .await?;
-
-    // Add the key pair to the keystore
-    keystore
-        .add_key(&AuthSecretKey::RpoFalcon512(key_pair))
-        .unwrap();
+) -> Result<Account, DevError> {
+    let token = TokenConfig {
+        symbol: symbol.to_string(),
+        decimals: 8,
+        max_supply: 1_000_000,
+    };
+    let faucet_account = dev::create_faucet(client, &keystore, &token).await?;
 
     println!("Faucet account ID: {:?}", faucet_account.id().to_hex());
-
-    // Resync to show newly deployed faucet
-    client.sync_state().await?;
     Ok(faucet_account)
 }
 
@@ -113,54 +50,12 @@ pub async fn mint_and_consume(
     faucet_account: Account,
     token_account: Account,
     amount: u64,
-) -> Result<(), ClientError> {
-    let fungible_asset = FungibleAsset::new(faucet_account.id(), amount).unwrap();
-
-    let transaction_request = TransactionRequestBuilder::mint_fungible_asset(
-        fungible_asset,
-        token_account.id(),
-        NoteType::Public,
-        client.rng(),
-    )
-    .unwrap()
-    .build()
-    .unwrap();
-
-    let tx_execution_result = client
-        .new_transaction(faucet_account.id(), transaction_request)
-        .await?;
-    client.submit_transaction(tx_execution_result).await?;
-
-    loop {
-        // Resync to get the latest data
-        client.sync_state().await?;
-
-        let consumable_notes = client
-            .get_consumable_notes(Some(token_account.id()))
-            .await?;
-        let list_of_note_ids: Vec<_> = consumable_notes.iter().map(|(note, _)| note.id()).collect();
-
-        if list_of_note_ids.len() == 1 {
-            let transaction_request = TransactionRequestBuilder::consume_notes(list_of_note_ids)
-                .build()
-                .unwrap();
-            let tx_execution_result = client
-                .new_transaction(token_account.id(), transaction_request)
-                .await?;
-
-            client.submit_transaction(tx_execution_result).await?;
-            break;
-        } else {
-            tokio::time::sleep(Duration::from_secs(3)).await;
-        }
-    }
-
-    client.sync_state().await?;
-    Ok(())
+) -> Result<(), DevError> {
+    dev::mint_and_consume(client, faucet_account.id(), token_account.id(), amount).await
 }
 
 pub async fn setup_test_user(
-    mut client: &mut Client,
+    client: &mut Client,
     keystore: FilesystemKeyStore<StdRng>,
     user_id: &str,
     faucet_a: Account,
@@ -170,15 +65,15 @@ pub async fn setup_test_user(
     let sync_summary = client.sync_state().await.unwrap();
     println!("Latest block: {}", sync_summary.block_num);
 
-    let account = create_account(&mut client, keystore.clone()).await.unwrap();
+    let account = create_account(client, keystore.clone()).await.unwrap();
 
     client.sync_state().await.unwrap();
     // Mint token A to the user
-    mint_and_consume(&mut client, faucet_a, account.clone(), amount)
+    mint_and_consume(client, faucet_a, account.clone(), amount)
         .await
         .unwrap();
 
-    mint_and_consume(&mut client, faucet_b, account.clone(), 20)
+    mint_and_consume(client, faucet_b, account.clone(), 20)
         .await
         .unwrap();
 
@@ -190,6 +85,79 @@ pub async fn setup_test_user(
     }
 }
 
+pub async fn delete_keystore_and_store() {
+    // Remove the SQLite store file
+
+    let keystore_dir: &str = "./keystore";
+    let store_path: &str = "./store.sqlite3";
+
+    if tokio::fs::metadata(store_path).await.is_ok() {
+        if let Err(e) = tokio::fs::remove_file(store_path).await {
+            eprintln!("failed to remove {}: {}", store_path, e);
+        }
+    } else {
+        println!("store not found: {}", store_path);
+    }
+
+    // Remove all files in the ./keystore directory
+    match tokio::fs::read_dir(keystore_dir).await {
+        Ok(mut dir) => {
+            while let Ok(Some(entry)) = dir.next_entry().await {
+                let file_path = entry.path();
+                if let Err(e) = tokio::fs::remove_file(&file_path).await {
+                    eprintln!("failed to remove {}: {}", file_path.display(), e);
+                }
+            }
+        }
+        Err(e) => eprintln!("failed to read directory {}: {}", keystore_dir, e),
+    }
+}
+
+/// Mock chain with the ETH and BTC faucets the SWAPp notes trade
+pub fn mock_chain() -> (MockChain, MockFungibleFaucet, MockFungibleFaucet) {
+    let mut chain = MockChain::new();
+    let faucet_a = chain.add_existing_faucet(Auth::BasicAuth, "ETH", 1_000_000, None);
+    let faucet_b = chain.add_existing_faucet(Auth::BasicAuth, "BTC", 1_000_000, None);
+    (chain, faucet_a, faucet_b)
+}
+
+/// Sets the note args of the transaction, one word per consumed SWAPp note
+pub fn set_note_args(tx_context: &mut TransactionContext, note_args: BTreeMap<NoteId, Word>) {
+    // keep the advice inputs, they hold the recipients of the expected output notes
+    let tx_script = tx_context.tx_args().tx_script().cloned();
+    let advice_inputs = tx_context.tx_args().advice_inputs().clone();
+    tx_context.set_tx_args(
+        TransactionArgs::new(tx_script, Some(note_args), AdviceMap::default())
+            .with_advice_inputs(advice_inputs),
+    );
+}
+
+/// Sends `amount` of the requested asset into the SWAPp note through its note args
+pub fn set_fill_amount(tx_context: &mut TransactionContext, note_id: NoteId, amount: u64) {
+    let note_args = [Felt::new(0), Felt::new(0), Felt::new(0), Felt::new(amount)];
+    set_note_args(tx_context, BTreeMap::from([(note_id, note_args)]));
+}
+
+/// Sends the filled amount and the price improvement of every fill into its SWAPp note
+pub fn set_fills<'a>(
+    tx_context: &mut TransactionContext,
+    fills: impl IntoIterator<Item = &'a SwapFill>,
+) {
+    let note_args = fills
+        .into_iter()
+        .map(|fill| {
+            let args = [
+                Felt::new(0),
+                Felt::new(0),
+                Felt::new(fill.improvement),
+                Felt::new(fill.filled_amount),
+            ];
+            (fill.order.id(), args)
+        })
+        .collect();
+    set_note_args(tx_context, note_args);
+}
+
 #[cfg(test)]
 mod tests {
     use miden_client::builder::ClientBuilder;
@@ -235,7 +203,7 @@ mod tests {
         let user = setup_test_user(
             &mut client,
             keystore.clone(),
-            &format!("testuser"),
+            "testuser",
             faucet_a.clone(),
             faucet_b.clone(),
             100,
@@ -244,31 +212,3 @@ mod tests {
         users.push(user);
     }
 }
-
-pub async fn delete_keystore_and_store() {
-    // Remove the SQLite store file
-
-    let keystore_dir: &str = &format!("./keystore");
-    let store_path: &str = &format!("./store.sqlite3");
-
-    if tokio::fs::metadata(store_path).await.is_ok() {
-        if let Err(e) = tokio::fs::remove_file(store_path).await {
-            eprintln!("failed to remove {}: {}", store_path, e);
-        }
-    } else {
-        println!("store not found: {}", store_path);
-    }
-
-    // Remove all files in the ./keystore directory
-    match tokio::fs::read_dir(keystore_dir).await {
-        Ok(mut dir) => {
-            while let Ok(Some(entry)) = dir.next_entry().await {
-                let file_path = entry.path();
-                if let Err(e) = tokio::fs::remove_file(&file_path).await {
-                    eprintln!("failed to remove {}: {}", file_path.display(), e);
-                }
-            }
-        }
-        Err(e) => eprintln!("failed to read directory {}: {}", keystore_dir, e),
-    }
-}